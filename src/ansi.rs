@@ -0,0 +1,283 @@
+use std::borrow::Cow;
+
+// Constants ------------------------------------------------------------------------------------- /
+
+const ESC: u8 = 0x1B;
+const BEL: u8 = 0x07;
+
+// C1 control characters are two bytes long when encoded as UTF-8 (`0xC2` followed by the code).
+const C1_LEAD: u8 = 0xC2;
+const C1_DCS: u8 = 0x90;
+const C1_SOS: u8 = 0x98;
+const C1_CSI: u8 = 0x9B;
+const C1_ST: u8 = 0x9C;
+const C1_OSC: u8 = 0x9D;
+const C1_PM: u8 = 0x9E;
+const C1_APC: u8 = 0x9F;
+
+// ANSI Segment ---------------------------------------------------------------------------------- /
+
+/// A piece of a string that is either visible text or a single, complete escape sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnsiSegment<'a> {
+    /// Text that is printed to the terminal
+    Text(&'a str),
+    /// An escape sequence (CSI, OSC, DCS, SOS, PM, APC or a simple `ESC` sequence)
+    Escape(&'a str),
+}
+
+/// Iterator splitting a string into text and escape sequence segments.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{ansi_segments, AnsiSegment};
+///
+/// let segments: Vec<AnsiSegment> = ansi_segments("\u{001B}[1mHi\u{001B}[0m").collect();
+/// assert_eq!(segments, vec![
+///     AnsiSegment::Escape("\u{001B}[1m"),
+///     AnsiSegment::Text("Hi"),
+///     AnsiSegment::Escape("\u{001B}[0m"),
+/// ]);
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnsiSegments<'a> {
+    remaining: &'a str,
+}
+
+impl<'a> Iterator for AnsiSegments<'a> {
+    type Item = AnsiSegment<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.remaining.is_empty() {
+            return None;
+        }
+        let bytes = self.remaining.as_bytes();
+        let (length, is_escape) = match escape_length(bytes) {
+            Some(length) => (length, true),
+            None => (next_escape_start(bytes).unwrap_or(bytes.len()), false),
+        };
+        let (segment, rest) = self.remaining.split_at(length);
+        self.remaining = rest;
+        if is_escape {
+            Some(AnsiSegment::Escape(segment))
+        } else {
+            Some(AnsiSegment::Text(segment))
+        }
+    }
+}
+
+/// Splits the given text into text and escape sequence segments.
+pub fn ansi_segments(text: &str) -> AnsiSegments<'_> {
+    AnsiSegments { remaining: text }
+}
+
+// Visible Chunks -------------------------------------------------------------------------------- /
+
+/// Iterator over the visible text between escape sequences. Each chunk borrows from the original string.
+#[derive(Debug, Clone)]
+pub struct VisibleChunks<'a> {
+    segments: AnsiSegments<'a>,
+}
+
+impl<'a> Iterator for VisibleChunks<'a> {
+    type Item = &'a str;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for segment in &mut self.segments {
+            if let AnsiSegment::Text(text) = segment {
+                return Some(text);
+            }
+        }
+        None
+    }
+}
+
+/// Iterates over the visible chunks of text, skipping all escape sequences (without copying).
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{visible_chunks, highlight_red};
+///
+/// let text = format!("a {} b", highlight_red("red"));
+/// let chunks: Vec<&str> = visible_chunks(&text).collect();
+/// assert_eq!(chunks, vec!["a ", "red", " b"]);
+/// ```
+pub fn visible_chunks(text: &str) -> VisibleChunks<'_> {
+    VisibleChunks { segments: ansi_segments(text) }
+}
+
+// Strip ----------------------------------------------------------------------------------------- /
+
+/// Removes all escape sequences (CSI, OSC, DCS, SOS, PM, APC and simple `ESC` sequences) from text.
+/// Returns the original text (without copying) if it contains no escape sequences.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{strip_ansi, highlight_bright_yellow};
+///
+/// let greeting = highlight_bright_yellow("Hello, World!");
+/// assert_eq!(strip_ansi(greeting.output()), "Hello, World!");
+/// ```
+pub fn strip_ansi(text: &str) -> Cow<'_, str> {
+    if next_escape_start(text.as_bytes()).is_none() {
+        return Cow::Borrowed(text);
+    }
+    Cow::Owned(visible_chunks(text).collect())
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Gets the codes of an SGR escape sequence (like `ESC[1;31m`), or `None` for any other sequence.
+/// Empty codes count as `0`, and codes too large for SGR are capped. Colon groups (like
+/// `38:2::255:0:0`) are read as one parameter and given as the equivalent semicolon codes.
+pub(crate) fn sgr_codes(escape: &str) -> Option<Vec<u8>> {
+    let body = escape.strip_prefix("\u{1B}[").or_else(|| escape.strip_prefix('\u{9B}'))?;
+    let params = body.strip_suffix('m')?;
+    if !params.bytes().all(|byte| byte.is_ascii_digit() || byte == b';' || byte == b':') {
+        return None;
+    }
+    let mut codes = Vec::new();
+    for param in params.split(';') {
+        let group: Vec<u8> = param.split(':').map(parse_code).collect();
+        push_group(&mut codes, &group);
+    }
+    Some(codes)
+}
+
+fn parse_code(param: &str) -> u8 {
+    param.parse::<u32>().map(|code| code.min(u8::MAX as u32) as u8).unwrap_or(0)
+}
+
+/// Adds the codes of a colon group (or a single code). Malformed colors are left out.
+pub(crate) fn push_group(codes: &mut Vec<u8>, group: &[u8]) {
+    match group {
+        [code] => codes.push(*code),
+        // Extended colors: `38:5:n`, and `38:2:r:g:b` with or without the color space id
+        [layer @ (38 | 48 | 58), 5, index] => codes.extend_from_slice(&[*layer, 5, *index]),
+        [layer @ (38 | 48 | 58), 2, .., red, green, blue] if group.len() <= 6 => {
+            codes.extend_from_slice(&[*layer, 2, *red, *green, *blue]);
+        }
+        [38 | 48 | 58, ..] => (),
+        // Underline styles: none, single, double, then curly, dotted and dashed (shown as single)
+        [4, 0] => codes.push(24),
+        [4, 2] => codes.push(21),
+        [4, _] => codes.push(4),
+        // Other sub-parameters aren't known, so only the code itself is kept
+        [code, ..] => codes.push(*code),
+        [] => (),
+    }
+}
+
+/// Finds the byte index of the next escape sequence introducer, if any.
+fn next_escape_start(bytes: &[u8]) -> Option<usize> {
+    (0..bytes.len()).find(|&index| escape_length(&bytes[index..]).is_some())
+}
+
+/// Returns the length of the escape sequence at the start of the given bytes,
+/// or `None` if the bytes do not start with an escape sequence.
+/// Unterminated sequences run to the end of the input.
+pub(crate) fn escape_length(bytes: &[u8]) -> Option<usize> {
+    match bytes {
+        [ESC, rest @ ..] => Some(1 + esc_body_length(rest)),
+        [C1_LEAD, code, rest @ ..] => {
+            let body = match *code {
+                C1_CSI => csi_length(rest),
+                C1_OSC => string_length(rest, true),
+                C1_DCS | C1_SOS | C1_PM | C1_APC => string_length(rest, false),
+                _ => return None,
+            };
+            Some(2 + body)
+        }
+        _ => None,
+    }
+}
+
+/// Length of what follows an `ESC` byte.
+fn esc_body_length(bytes: &[u8]) -> usize {
+    match bytes.first() {
+        Some(b'[') => 1 + csi_length(&bytes[1..]),
+        Some(b']') => 1 + string_length(&bytes[1..], true),
+        Some(b'P') | Some(b'X') | Some(b'^') | Some(b'_') => 1 + string_length(&bytes[1..], false),
+        Some(0x20..=0x2F) => {
+            // Intermediate bytes, followed by a final byte
+            let intermediates = bytes.iter().take_while(|byte| (0x20..=0x2F).contains(*byte)).count();
+            match bytes.get(intermediates) {
+                Some(0x30..=0x7E) => intermediates + 1,
+                _ => intermediates,
+            }
+        }
+        Some(0x30..=0x7E) => 1,
+        _ => 0,
+    }
+}
+
+/// Length of a CSI sequence body (parameters, intermediates and final byte).
+/// The sequence ends early at the first byte that can't be part of it.
+fn csi_length(bytes: &[u8]) -> usize {
+    for (index, byte) in bytes.iter().enumerate() {
+        match byte {
+            0x20..=0x3F => continue,
+            0x40..=0x7E => return index + 1,
+            _ => return index,
+        }
+    }
+    bytes.len()
+}
+
+/// Length of a control string body, including its terminator (ST, or BEL for OSC).
+fn string_length(bytes: &[u8], allow_bel: bool) -> usize {
+    let mut index = 0;
+    while index < bytes.len() {
+        match bytes[index] {
+            BEL if allow_bel => return index + 1,
+            ESC if bytes.get(index + 1) == Some(&b'\\') => return index + 2,
+            C1_LEAD if bytes.get(index + 1) == Some(&C1_ST) => return index + 2,
+            _ => index += 1,
+        }
+    }
+    bytes.len()
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert!(matches!(strip_ansi("plain text"), Cow::Borrowed("plain text")));
+        assert_eq!(strip_ansi("\u{001B}[1;93mHello\u{001B}[0m, World!"), "Hello, World!");
+        assert_eq!(strip_ansi("a\u{001B}]0;title\u{0007}b"), "ab");
+        assert_eq!(strip_ansi("a\u{001B}]8;;https://example.com\u{001B}\\link\u{001B}]8;;\u{001B}\\b"), "alinkb");
+        assert_eq!(strip_ansi("a\u{001B}Pq#0;1;2\u{001B}\\b"), "ab");
+        assert_eq!(strip_ansi("a\u{001B}_apc\u{009C}\u{001B}^pm\u{001B}\\\u{001B}Xsos\u{001B}\\b"), "ab");
+        assert_eq!(strip_ansi("a\u{001B}7b\u{001B}8c\u{001B}(Bd"), "abcd");
+        assert_eq!(strip_ansi("a\u{009B}31mb\u{009D}0;t\u{0007}c"), "abc");
+        assert_eq!(strip_ansi("unterminated\u{001B}]0;title"), "unterminated");
+    }
+
+    #[test]
+    fn test_sgr_codes() {
+        assert_eq!(sgr_codes("\u{001B}[1;93m"), Some(vec![1, 93]));
+        assert_eq!(sgr_codes("\u{001B}[m"), Some(vec![0]));
+        assert_eq!(sgr_codes("\u{001B}[;38:5:183m"), Some(vec![0, 38, 5, 183]));
+        assert_eq!(sgr_codes("\u{001B}[38:2::255:0:0m"), Some(vec![38, 2, 255, 0, 0]));
+        assert_eq!(sgr_codes("\u{001B}[1;48:2:10:20:30m"), Some(vec![1, 48, 2, 10, 20, 30]));
+        assert_eq!(sgr_codes("\u{001B}[4:3;4:0;4:2m"), Some(vec![4, 24, 21]));
+        assert_eq!(sgr_codes("\u{001B}[38:2:1m"), Some(vec![]));
+        assert_eq!(sgr_codes("\u{001B}[2J"), None);
+        assert_eq!(sgr_codes("\u{001B}[?1m"), None);
+    }
+
+    #[test]
+    fn test_segments() {
+        let segments: Vec<AnsiSegment> = ansi_segments("\u{001B}[31mé\u{001B}[0m!").collect();
+        assert_eq!(segments, vec![
+            AnsiSegment::Escape("\u{001B}[31m"),
+            AnsiSegment::Text("é"),
+            AnsiSegment::Escape("\u{001B}[0m"),
+            AnsiSegment::Text("!"),
+        ]);
+    }
+}
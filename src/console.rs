@@ -0,0 +1,165 @@
+use crate::detect::Stream;
+use crate::render::{override_render_policy, RenderPolicy};
+use crate::terminal_style::TerminalStyle;
+use crate::writer::StyledWriter;
+use std::fmt::Arguments;
+use std::io::{self, BufWriter, StderrLock, StdoutLock, Write};
+
+// Styled Streams -------------------------------------------------------------------------------- /
+
+/// Locked, buffered standard output that writes styled text (see `stdout`)
+pub type StyledStdout = StyledWriter<BufWriter<StdoutLock<'static>>>;
+
+/// Locked, buffered standard error that writes styled text (see `stderr`)
+pub type StyledStderr = StyledWriter<BufWriter<StderrLock<'static>>>;
+
+/// Locks standard output for writing styled text, buffered until it's flushed or dropped.
+///
+/// Whether escape codes are written, and with how many colors, is decided for standard output
+/// alone (see `color_level`), so output piped to a file stays plain while standard error
+/// still shows colors. The decision is made once, then used by every handle.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use terminal_text_styler::{stdout, TerminalStyle};
+///
+/// let mut out = stdout();
+/// out.write_styled("done", &TerminalStyle::green()).unwrap();
+/// writeln!(out, " in 3s").unwrap();
+/// ```
+pub fn stdout() -> StyledStdout {
+    StyledWriter::for_stream(BufWriter::new(io::stdout().lock()), Stream::Stdout)
+}
+
+/// Locks standard error for writing styled text, buffered until it's flushed or dropped.
+/// Whether escape codes are written is decided for standard error alone (see `stdout`).
+pub fn stderr() -> StyledStderr {
+    StyledWriter::for_stream(BufWriter::new(io::stderr().lock()), Stream::Stderr)
+}
+
+// Printing -------------------------------------------------------------------------------------- /
+
+/// Prints formatted text in a style to a stream, used by `styled_print!` and the other macros.
+/// Styled values in the text (like `Styled` or `StyledLine`) are written for the stream's color
+/// level too. Panics if writing fails, like `print!`.
+#[doc(hidden)]
+pub fn _print_styled(stream: Stream, style: &TerminalStyle, args: Arguments<'_>, newline: bool) {
+    let result = match stream {
+        Stream::Stdout => print_to(stdout(), RenderPolicy::auto(stream), style, args, newline),
+        Stream::Stderr => print_to(stderr(), RenderPolicy::auto(stream), style, args, newline),
+    };
+    if let Err(error) = result {
+        let name = if stream == Stream::Stdout { "stdout" } else { "stderr" };
+        panic!("failed printing to {}: {}", name, error);
+    }
+}
+
+/// Prints to a writer, with styled values in the text written for the policy (which should give
+/// the writer's color level)
+fn print_to<W: Write>(mut writer: StyledWriter<W>, policy: RenderPolicy, style: &TerminalStyle, args: Arguments<'_>, newline: bool) -> io::Result<()> {
+    let _policy = override_render_policy(policy);
+    writer.set_style(style);
+    writer.write_fmt(args)?;
+    // The newline isn't styled, so backgrounds don't run to the end of the line
+    writer.reset()?;
+    if newline {
+        writer.write_all(b"\n")?;
+    }
+    writer.flush()
+}
+
+/// Prints formatted text in a style to standard output, with the color level of standard output
+/// (see `stdout`).
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{styled_print, TerminalStyle};
+///
+/// styled_print!(&TerminalStyle::bright_yellow(), "{} warnings", 3);
+/// ```
+#[macro_export]
+macro_rules! styled_print {
+    ($style:expr, $($arg:tt)*) => {
+        $crate::_print_styled($crate::Stream::Stdout, $style, format_args!($($arg)*), false)
+    };
+}
+
+/// Prints formatted text in a style to standard output, then a newline (see `styled_print!`).
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{styled_println, TerminalStyle};
+///
+/// styled_println!(&TerminalStyle::green(), "built {} crates", 12);
+/// ```
+#[macro_export]
+macro_rules! styled_println {
+    ($style:expr) => {
+        $crate::_print_styled($crate::Stream::Stdout, $style, format_args!(""), true)
+    };
+    ($style:expr, $($arg:tt)*) => {
+        $crate::_print_styled($crate::Stream::Stdout, $style, format_args!($($arg)*), true)
+    };
+}
+
+/// Prints formatted text in a style to standard error, with the color level of standard error
+/// (see `stderr`).
+#[macro_export]
+macro_rules! styled_eprint {
+    ($style:expr, $($arg:tt)*) => {
+        $crate::_print_styled($crate::Stream::Stderr, $style, format_args!($($arg)*), false)
+    };
+}
+
+/// Prints formatted text in a style to standard error, then a newline (see `styled_eprint!`).
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{styled_eprintln, TerminalStyle};
+///
+/// styled_eprintln!(&TerminalStyle::bright_red(), "error: {} not found", "foo.rs");
+/// ```
+#[macro_export]
+macro_rules! styled_eprintln {
+    ($style:expr) => {
+        $crate::_print_styled($crate::Stream::Stderr, $style, format_args!(""), true)
+    };
+    ($style:expr, $($arg:tt)*) => {
+        $crate::_print_styled($crate::Stream::Stderr, $style, format_args!($($arg)*), true)
+    };
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ColorLevel, StyledLine, Stylize};
+
+    #[test]
+    fn test_print_to() {
+        let mut output = Vec::new();
+        let style = TerminalStyle::from(vec![48, 2, 0, 0, 255]);
+        let args = format_args!("{} {}", "a".rgb(255, 0, 0), 1);
+        let policy = RenderPolicy::always().max_level(ColorLevel::Ansi256);
+        print_to(StyledWriter::with_color_level(&mut output, ColorLevel::Ansi256), policy, &style, args, true).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\u{001B}[48;5;21m\u{001B}[38;5;196ma\u{001B}[0m\u{001B}[48;5;21m 1\u{001B}[0m\n"
+        );
+        let mut output = Vec::new();
+        print_to(StyledWriter::with_color_level(&mut output, ColorLevel::None), RenderPolicy::never(), &style, args, false).unwrap();
+        assert_eq!(output, b"a 1");
+        let mut output = Vec::new();
+        let mut line = StyledLine::from("b".green());
+        line.push_styled("c", TerminalStyle::blue());
+        let red = TerminalStyle::red();
+        let args = format_args!("{}{}!", "a".style(&red), line);
+        print_to(StyledWriter::new(&mut output), RenderPolicy::always(), &TerminalStyle::from(vec![1]), args, false).unwrap();
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "\u{001B}[1m\u{001B}[0;31ma\u{001B}[0m\u{001B}[1m\u{001B}[32mb\u{001B}[0;34mc\u{001B}[0m\u{001B}[1m!\u{001B}[0m"
+        );
+    }
+}
@@ -0,0 +1,167 @@
+use crate::terminfo::Terminfo;
+use std::env;
+use std::io::{self, IsTerminal};
+
+// Color Level ----------------------------------------------------------------------------------- /
+
+/// How many colors a terminal can show. Levels are ordered, so `ColorLevel::Ansi256 > ColorLevel::Ansi16`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ColorLevel {
+    /// No colors or other styles should be used
+    None,
+    /// The 16 basic colors (codes `30`-`37`, `90`-`97` and their backgrounds)
+    Ansi16,
+    /// ANSI 256-colors (`38;5;n`)
+    Ansi256,
+    /// 24-bit colors (`38;2;r;g;b`)
+    TrueColor,
+}
+
+/// An output stream of the process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Stream {
+    /// Standard output
+    Stdout,
+    /// Standard error
+    Stderr,
+}
+
+impl Stream {
+
+    /// Whether the stream is connected to a terminal
+    pub fn is_terminal(&self) -> bool {
+        match self {
+            Stream::Stdout => io::stdout().is_terminal(),
+            Stream::Stderr => io::stderr().is_terminal(),
+        }
+    }
+}
+
+// Detection ------------------------------------------------------------------------------------- /
+
+/// Detects the color level to use for a stream, from the environment variables of the process,
+/// the terminfo entry of the terminal (if there is one) and whether the stream is a terminal.
+/// See `detect_color_level_with`.
+pub fn color_level(stream: Stream) -> ColorLevel {
+    let terminfo = Terminfo::from_env().ok();
+    detect_color_level_with(stream.is_terminal(), |name| env::var(name).ok(), terminfo.as_ref())
+}
+
+/// Detects the color level to use, given whether the output is a terminal and a function that
+/// looks up environment variables (so it can be tested without changing the environment).
+///
+/// The conventions are checked in this order:
+/// - `FORCE_COLOR`: `0` or `false` disables colors. Any other value enables them, even when the
+///   output is not a terminal: `2` for at least 256 colors, `3` for 24-bit colors.
+/// - `CLICOLOR_FORCE`: any value other than `0` enables colors, even when the output is not a terminal.
+/// - `NO_COLOR`: any value (other than an empty one) disables colors.
+/// - `CLICOLOR`: `0` disables colors.
+/// - Colors are disabled when the output is not a terminal, or when `TERM` is `dumb`.
+///
+/// When colors are used, `COLORTERM` (`truecolor` or `24bit`) and `TERM` (like `xterm-256color`
+/// or `xterm-direct`) tell how many.
+///
+/// # Examples
+/// ```
+/// use std::collections::HashMap;
+/// use terminal_text_styler::{detect_color_level, ColorLevel};
+///
+/// let env: HashMap<&str, &str> = vec![("TERM", "xterm-256color")].into_iter().collect();
+/// let lookup = |name: &str| env.get(name).map(|value| value.to_string());
+/// assert_eq!(detect_color_level(true, lookup), ColorLevel::Ansi256);
+/// assert_eq!(detect_color_level(false, lookup), ColorLevel::None);
+/// ```
+pub fn detect_color_level<F>(is_terminal: bool, var: F) -> ColorLevel
+where
+    F: Fn(&str) -> Option<String>,
+{
+    detect_color_level_with(is_terminal, var, None)
+}
+
+/// Detects the color level to use like `detect_color_level`, but using the capabilities of the
+/// terminal's terminfo entry (its `colors` and the `Tc` and `RGB` extensions) instead of guessing
+/// them from `TERM`. `COLORTERM` and the conventions that force or disable colors still apply.
+///
+/// # Examples
+/// ```no_run
+/// use terminal_text_styler::{detect_color_level_with, ColorLevel, Terminfo};
+///
+/// // The entry tells that `xterm-kitty` supports 24-bit colors, though `COLORTERM` isn't set
+/// let terminfo = Terminfo::from_name("xterm-kitty").unwrap();
+/// let level = detect_color_level_with(true, |_| None, Some(&terminfo));
+/// assert_eq!(level, ColorLevel::TrueColor);
+/// ```
+pub fn detect_color_level_with<F>(is_terminal: bool, var: F, terminfo: Option<&Terminfo>) -> ColorLevel
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let forced = match var("FORCE_COLOR").as_deref().map(str::trim) {
+        Some("0") | Some("false") => return ColorLevel::None,
+        Some("2") => Some(ColorLevel::Ansi256),
+        Some("3") => Some(ColorLevel::TrueColor),
+        Some(_) => Some(ColorLevel::Ansi16),
+        None => None,
+    };
+    let forced = forced.or_else(|| {
+        var("CLICOLOR_FORCE")
+            .filter(|value| !value.is_empty() && value != "0")
+            .map(|_| ColorLevel::Ansi16)
+    });
+    let term = var("TERM").unwrap_or_default();
+    if forced.is_none() {
+        let no_color = var("NO_COLOR").is_some_and(|value| !value.is_empty());
+        let clicolor_off = var("CLICOLOR").as_deref() == Some("0");
+        if no_color || clicolor_off || !is_terminal || term == "dumb" {
+            return ColorLevel::None;
+        }
+    }
+    let colorterm = var("COLORTERM").unwrap_or_default().to_lowercase();
+    let supported = if colorterm == "truecolor" || colorterm == "24bit" {
+        ColorLevel::TrueColor
+    } else if let Some(terminfo) = terminfo {
+        terminfo.color_level()
+    } else if term.ends_with("-direct") || term.ends_with("-truecolor") {
+        ColorLevel::TrueColor
+    } else if term.contains("256color") {
+        ColorLevel::Ansi256
+    } else {
+        ColorLevel::Ansi16
+    };
+    supported.max(forced.unwrap_or(ColorLevel::None))
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    fn detect(is_terminal: bool, vars: &[(&str, &str)]) -> ColorLevel {
+        let env: HashMap<&str, &str> = vars.iter().cloned().collect();
+        detect_color_level(is_terminal, |name| env.get(name).map(|value| value.to_string()))
+    }
+
+    #[test]
+    fn test_terminal_levels() {
+        assert_eq!(detect(true, &[]), ColorLevel::Ansi16);
+        assert_eq!(detect(true, &[("TERM", "dumb")]), ColorLevel::None);
+        assert_eq!(detect(true, &[("TERM", "screen-256color")]), ColorLevel::Ansi256);
+        assert_eq!(detect(true, &[("TERM", "xterm-direct")]), ColorLevel::TrueColor);
+        assert_eq!(detect(true, &[("TERM", "xterm"), ("COLORTERM", "24bit")]), ColorLevel::TrueColor);
+        assert_eq!(detect(false, &[("COLORTERM", "truecolor")]), ColorLevel::None);
+    }
+
+    #[test]
+    fn test_conventions() {
+        assert_eq!(detect(true, &[("NO_COLOR", "1")]), ColorLevel::None);
+        assert_eq!(detect(true, &[("NO_COLOR", "")]), ColorLevel::Ansi16);
+        assert_eq!(detect(true, &[("CLICOLOR", "0")]), ColorLevel::None);
+        assert_eq!(detect(false, &[("CLICOLOR_FORCE", "1"), ("NO_COLOR", "1")]), ColorLevel::Ansi16);
+        assert_eq!(detect(false, &[("CLICOLOR_FORCE", "0")]), ColorLevel::None);
+        assert_eq!(detect(false, &[("FORCE_COLOR", "")]), ColorLevel::Ansi16);
+        assert_eq!(detect(false, &[("FORCE_COLOR", "3"), ("TERM", "dumb")]), ColorLevel::TrueColor);
+        assert_eq!(detect(true, &[("FORCE_COLOR", "2"), ("COLORTERM", "truecolor")]), ColorLevel::TrueColor);
+        assert_eq!(detect(true, &[("FORCE_COLOR", "false"), ("CLICOLOR_FORCE", "1")]), ColorLevel::None);
+    }
+}
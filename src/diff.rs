@@ -0,0 +1,370 @@
+use crate::styled_line::StyledLine;
+use crate::terminal_style::TerminalStyle;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Diff Options ---------------------------------------------------------------------------------- /
+
+/// The pieces that text is split into before comparing it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffGranularity {
+    /// Compares characters (grapheme clusters, so accents stay with their letter)
+    Char,
+    /// Compares words, whitespace and punctuation (using Unicode word boundaries)
+    Word,
+    /// Compares lines (each including its newline)
+    Line,
+}
+
+/// Options used to show the differences between two strings as styled text.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{DiffOptions, DiffGranularity, TerminalStyle};
+///
+/// let options = DiffOptions {
+///     granularity: DiffGranularity::Char,
+///     equal: TerminalStyle::from(vec![2]),
+///     ..DiffOptions::default()
+/// };
+/// assert_eq!(options.insert, TerminalStyle::green());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffOptions {
+    /// The pieces that are compared
+    pub granularity: DiffGranularity,
+    /// Style of text found in both strings (un-styled by default)
+    pub equal: TerminalStyle,
+    /// Style of text only found in the old string (red by default)
+    pub delete: TerminalStyle,
+    /// Style of text only found in the new string (green by default)
+    pub insert: TerminalStyle,
+}
+
+impl Default for DiffOptions {
+
+    /// Compares words, with deleted text in red and inserted text in green
+    fn default() -> Self {
+        DiffOptions {
+            granularity: DiffGranularity::Word,
+            equal: TerminalStyle::no_color(),
+            delete: TerminalStyle::red(),
+            insert: TerminalStyle::green(),
+        }
+    }
+}
+
+// Diff ------------------------------------------------------------------------------------------ /
+
+/// A run of text in a diff.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp<'a> {
+    /// Text found in both strings
+    Equal(&'a str),
+    /// Text only found in the old string
+    Delete(&'a str),
+    /// Text only found in the new string
+    Insert(&'a str),
+}
+
+/// Finds the shortest list of changes that turns the old string into the new one (using the Myers
+/// diff algorithm), as runs of equal, deleted and inserted text. Within each changed part, the
+/// deleted text comes before the inserted text. Memory use grows linearly with the length of the
+/// strings, and for very different strings the search is cut short, giving a longer list.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{diff, DiffGranularity, DiffOp};
+///
+/// assert_eq!(
+///     diff("timeout = 30", "timeout = 45", DiffGranularity::Word),
+///     vec![DiffOp::Equal("timeout = "), DiffOp::Delete("30"), DiffOp::Insert("45")]
+/// );
+/// ```
+pub fn diff<'a>(old: &'a str, new: &'a str, granularity: DiffGranularity) -> Vec<DiffOp<'a>> {
+    let old_tokens = tokens(old, granularity);
+    let new_tokens = tokens(new, granularity);
+    let old_texts: Vec<&str> = old_tokens.iter().map(|range| &old[range.clone()]).collect();
+    let new_texts: Vec<&str> = new_tokens.iter().map(|range| &new[range.clone()]).collect();
+    let mut ops = Vec::new();
+    // Byte ranges of the current runs of each kind of text
+    let mut deleted: Option<Range<usize>> = None;
+    let mut inserted: Option<Range<usize>> = None;
+    let mut equal: Option<Range<usize>> = None;
+    for edit in shortest_edit(&old_texts, &new_texts) {
+        match edit {
+            Edit::Equal(index) => {
+                push_change(&mut ops, old, new, deleted.take(), inserted.take());
+                extend(&mut equal, &old_tokens[index]);
+            }
+            Edit::Delete(index) => {
+                push_equal(&mut ops, old, equal.take());
+                extend(&mut deleted, &old_tokens[index]);
+            }
+            Edit::Insert(index) => {
+                push_equal(&mut ops, old, equal.take());
+                extend(&mut inserted, &new_tokens[index]);
+            }
+        }
+    }
+    push_change(&mut ops, old, new, deleted, inserted);
+    push_equal(&mut ops, old, equal);
+    ops
+}
+
+/// Shows the differences between two strings as styled text, with each run of equal, deleted
+/// and inserted text in the style given by the options.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{diff_styled, DiffOptions};
+///
+/// let line = diff_styled("level: warn", "level: debug", &DiffOptions::default());
+/// assert_eq!(line.plain(), "level: warndebug");
+/// assert_eq!(line.output(), "level: \u{001B}[0;31mwarn\u{001B}[0;32mdebug\u{001B}[0m");
+/// ```
+pub fn diff_styled(old: &str, new: &str, options: &DiffOptions) -> StyledLine {
+    render_diff(&diff(old, new, options.granularity), options)
+}
+
+/// Shows a diff as styled text, using the styles of the options (see `diff_styled`)
+pub fn render_diff(ops: &[DiffOp<'_>], options: &DiffOptions) -> StyledLine {
+    let mut line = StyledLine::new();
+    for op in ops {
+        let (text, style) = match *op {
+            DiffOp::Equal(text) => (text, &options.equal),
+            DiffOp::Delete(text) => (text, &options.delete),
+            DiffOp::Insert(text) => (text, &options.insert),
+        };
+        line.push_styled(text, style.clone());
+    }
+    line
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// An edit of the shortest edit script, by token index
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Edit {
+    /// Index in the old tokens
+    Equal(usize),
+    /// Index in the old tokens
+    Delete(usize),
+    /// Index in the new tokens
+    Insert(usize),
+}
+
+/// Splits text into byte ranges of the pieces to compare
+fn tokens(text: &str, granularity: DiffGranularity) -> Vec<Range<usize>> {
+    let pieces: Vec<(usize, &str)> = match granularity {
+        DiffGranularity::Char => text.grapheme_indices(true).collect(),
+        DiffGranularity::Word => text.split_word_bound_indices().collect(),
+        DiffGranularity::Line => text.split_inclusive('\n')
+            .scan(0, |offset, line| {
+                let start = *offset;
+                *offset += line.len();
+                Some((start, line))
+            })
+            .collect(),
+    };
+    pieces.into_iter().map(|(start, piece)| start..start + piece.len()).collect()
+}
+
+/// Edits searched for before the middle of a part is chosen without finishing the search, which
+/// keeps very different inputs from taking quadratic time (the script is still correct, just
+/// not always the shortest)
+const MAX_COST: usize = 1024;
+
+/// Finds the shortest edit script between two lists of tokens, with the linear space version of
+/// the Myers algorithm (which splits the lists at the middle of the path and diffs each half)
+fn shortest_edit(old: &[&str], new: &[&str]) -> Vec<Edit> {
+    let mut script = Vec::new();
+    let mut furthest = Furthest::default();
+    edit_between(old, new, 0, 0, &mut furthest, &mut script);
+    script
+}
+
+/// The furthest x reached on each diagonal, searching forward and backward
+#[derive(Default)]
+struct Furthest {
+    forward: Vec<usize>,
+    backward: Vec<usize>,
+}
+
+/// Adds the edit script of the lists, which start at the given indices of the whole lists
+fn edit_between(old: &[&str], new: &[&str], old_start: usize, new_start: usize, furthest: &mut Furthest, script: &mut Vec<Edit>) {
+    let prefix = old.iter().zip(new).take_while(|(old, new)| old == new).count();
+    script.extend((0..prefix).map(|index| Edit::Equal(old_start + index)));
+    let (old, new) = (&old[prefix..], &new[prefix..]);
+    let (old_start, new_start) = (old_start + prefix, new_start + prefix);
+    let suffix = old.iter().rev().zip(new.iter().rev()).take_while(|(old, new)| old == new).count();
+    let (old, new) = (&old[..old.len() - suffix], &new[..new.len() - suffix]);
+    if old.is_empty() {
+        script.extend((0..new.len()).map(|index| Edit::Insert(new_start + index)));
+    } else if new.is_empty() {
+        script.extend((0..old.len()).map(|index| Edit::Delete(old_start + index)));
+    } else {
+        let (x, y) = middle(old, new, furthest);
+        edit_between(&old[..x], &new[..y], old_start, new_start, furthest, script);
+        edit_between(&old[x..], &new[y..], old_start + x, new_start + y, furthest, script);
+    }
+    script.extend((old.len()..old.len() + suffix).map(|index| Edit::Equal(old_start + index)));
+}
+
+/// Finds a point in the middle of the shortest path through the edit graph, by searching from
+/// both ends until the searches meet. Both lists must be non-empty and differ at each end.
+fn middle(old: &[&str], new: &[&str], furthest: &mut Furthest) -> (usize, usize) {
+    let (old_len, new_len) = (old.len() as isize, new.len() as isize);
+    let delta = old_len - new_len;
+    let max = (old_len + new_len + 1) / 2;
+    // Diagonal k (x - y) is stored at k + max + 1, in both directions
+    let index = |k: isize| (k + max + 1) as usize;
+    let size = 2 * max as usize + 3;
+    furthest.forward.clear();
+    furthest.forward.resize(size, 0);
+    furthest.backward.clear();
+    furthest.backward.resize(size, 0);
+    let (forward, backward) = (&mut furthest.forward, &mut furthest.backward);
+    for edits in 0..=max {
+        if edits as usize > MAX_COST {
+            // Gives up on the shortest path, splitting at the furthest point reached so far (only
+            // points inside the lists, past the start, split them)
+            return (-edits + 1..edits).step_by(2)
+                .map(|k| (forward[index(k)] as isize, forward[index(k)] as isize - k))
+                .filter(|(x, y)| *x <= old_len && (0..=new_len).contains(y) && x + y > 0)
+                .max_by_key(|(x, y)| x + y)
+                .map_or((1, 0), |(x, y)| (x as usize, y as usize));
+        }
+        for k in (-edits..=edits).step_by(2) {
+            let mut x = if k == -edits || (k != edits && forward[index(k - 1)] < forward[index(k + 1)]) {
+                forward[index(k + 1)] as isize
+            } else {
+                forward[index(k - 1)] as isize + 1
+            };
+            let mut y = x - k;
+            let start = (x, y);
+            while x < old_len && y < new_len && old[x as usize] == new[y as usize] {
+                x += 1;
+                y += 1;
+            }
+            forward[index(k)] = x as usize;
+            // With an odd delta, the paths meet while searching forward
+            let reverse_k = delta - k;
+            if delta % 2 != 0 && reverse_k.abs() < edits && x + backward[index(reverse_k)] as isize >= old_len {
+                // Either end of the snake is on the path, but only one inside the lists splits them
+                let (x, y) = if start == (0, 0) || start == (old_len, new_len) { (x, y) } else { start };
+                return (x as usize, y as usize);
+            }
+        }
+        for k in (-edits..=edits).step_by(2) {
+            // Searches the reversed lists, so x counts tokens from the end
+            let mut x = if k == -edits || (k != edits && backward[index(k - 1)] < backward[index(k + 1)]) {
+                backward[index(k + 1)] as isize
+            } else {
+                backward[index(k - 1)] as isize + 1
+            };
+            let mut y = x - k;
+            while x < old_len && y < new_len && old[(old_len - 1 - x) as usize] == new[(new_len - 1 - y) as usize] {
+                x += 1;
+                y += 1;
+            }
+            backward[index(k)] = x as usize;
+            let forward_k = delta - k;
+            if delta % 2 == 0 && forward_k.abs() <= edits && x + forward[index(forward_k)] as isize >= old_len {
+                return ((old_len - x) as usize, (new_len - y) as usize);
+            }
+        }
+    }
+    // The searches always meet before this, but deleting and inserting everything is also correct
+    (old.len(), 0)
+}
+
+/// Extends a byte range to cover the next range (or starts it)
+fn extend(run: &mut Option<Range<usize>>, next: &Range<usize>) {
+    match run {
+        Some(range) => range.end = next.end,
+        None => *run = Some(next.clone()),
+    }
+}
+
+fn push_equal<'a>(ops: &mut Vec<DiffOp<'a>>, old: &'a str, equal: Option<Range<usize>>) {
+    if let Some(range) = equal {
+        ops.push(DiffOp::Equal(&old[range]));
+    }
+}
+
+fn push_change<'a>(ops: &mut Vec<DiffOp<'a>>, old: &'a str, new: &'a str, deleted: Option<Range<usize>>, inserted: Option<Range<usize>>) {
+    if let Some(range) = deleted {
+        ops.push(DiffOp::Delete(&old[range]));
+    }
+    if let Some(range) = inserted {
+        ops.push(DiffOp::Insert(&new[range]));
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_diff_granularity() {
+        assert_eq!(diff("", "", DiffGranularity::Char), vec![]);
+        assert_eq!(diff("abc", "", DiffGranularity::Char), vec![DiffOp::Delete("abc")]);
+        assert_eq!(
+            diff("kitten", "sitting", DiffGranularity::Char),
+            vec![
+                DiffOp::Delete("k"), DiffOp::Insert("s"), DiffOp::Equal("itt"),
+                DiffOp::Delete("e"), DiffOp::Insert("i"), DiffOp::Equal("n"), DiffOp::Insert("g"),
+            ]
+        );
+        assert_eq!(
+            diff("the quick fox", "the slow fox", DiffGranularity::Word),
+            vec![DiffOp::Equal("the "), DiffOp::Delete("quick"), DiffOp::Insert("slow"), DiffOp::Equal(" fox")]
+        );
+        assert_eq!(
+            diff("a\nb\nc\n", "a\nc\nd", DiffGranularity::Line),
+            vec![DiffOp::Equal("a\n"), DiffOp::Delete("b\n"), DiffOp::Equal("c\n"), DiffOp::Insert("d")]
+        );
+    }
+
+    #[test]
+    fn test_diff_reconstructs() {
+        let (old, new) = ("The café is open late on weekdays", "A café is closed on weekdays, sadly");
+        for granularity in [DiffGranularity::Char, DiffGranularity::Word, DiffGranularity::Line] {
+            let ops = diff(old, new, granularity);
+            let side = |keep_insert: bool| -> String {
+                ops.iter().filter_map(|op| match *op {
+                    DiffOp::Equal(text) => Some(text),
+                    DiffOp::Delete(text) if !keep_insert => Some(text),
+                    DiffOp::Insert(text) if keep_insert => Some(text),
+                    _ => None,
+                }).collect()
+            };
+            assert_eq!(side(false), old);
+            assert_eq!(side(true), new);
+        }
+    }
+
+    #[test]
+    fn test_diff_unrelated_inputs() {
+        let old: String = (0..5000u32).map(|index| (b'a' + (index * 7 % 26) as u8) as char).collect();
+        let new: String = (0..5000u32).map(|index| (b'a' + (index * 11 % 26) as u8) as char).collect();
+        let ops = diff(&old, &new, DiffGranularity::Char);
+        let old_side: String = ops.iter().filter_map(|op| match *op {
+            DiffOp::Equal(text) | DiffOp::Delete(text) => Some(text),
+            DiffOp::Insert(_) => None,
+        }).collect();
+        let new_side: String = ops.iter().filter_map(|op| match *op {
+            DiffOp::Equal(text) | DiffOp::Insert(text) => Some(text),
+            DiffOp::Delete(_) => None,
+        }).collect();
+        assert_eq!(old_side, old);
+        assert_eq!(new_side, new);
+        // Lists of very different lengths, where the searches run far outside the shorter one
+        let (old, new) = ("a".repeat(100), "b".repeat(3000));
+        let ops = diff(&old, &new, DiffGranularity::Char);
+        assert_eq!(ops, vec![DiffOp::Delete(&old), DiffOp::Insert(&new)]);
+    }
+}
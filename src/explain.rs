@@ -0,0 +1,310 @@
+use crate::ansi::{ansi_segments, AnsiSegment};
+use crate::palette::ansi256_to_rgb;
+use crate::parser::{AnsiEvent, AnsiParser};
+use crate::{ANSIBackgroundColor, ANSIForegroundColor, SGREffect};
+use std::fmt::{Display, Error, Formatter};
+
+// Escape Explanation ---------------------------------------------------------------------------- /
+
+/// A human-readable description of a single escape sequence found in some text.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::explain;
+///
+/// let explanations = explain("\u{001B}[1;38;5;183mHi\u{001B}[0m");
+/// assert_eq!(explanations[0].description(), "bold, foreground 256-color #183 (≈ #d7afff)");
+/// assert_eq!(explanations[1].to_string(), "ESC[0m: normal/reset");
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeExplanation<'a> {
+    offset: usize,
+    sequence: &'a str,
+    description: String,
+}
+
+impl<'a> EscapeExplanation<'a> {
+
+    /// Gets the byte offset of the sequence in the explained text
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+
+    /// Gets the raw escape sequence
+    pub fn sequence(&self) -> &'a str {
+        self.sequence
+    }
+
+    /// Gets the escape sequence with control characters spelled out (like `ESC[0m`)
+    pub fn visible_sequence(&self) -> String {
+        let mut visible = String::with_capacity(self.sequence.len() + 2);
+        for character in self.sequence.chars() {
+            match character {
+                '\u{1B}' => visible.push_str("ESC"),
+                '\u{07}' => visible.push_str("BEL"),
+                '\u{90}' => visible.push_str("DCS"),
+                '\u{98}' => visible.push_str("SOS"),
+                '\u{9B}' => visible.push_str("CSI"),
+                '\u{9C}' => visible.push_str("ST"),
+                '\u{9D}' => visible.push_str("OSC"),
+                '\u{9E}' => visible.push_str("PM"),
+                '\u{9F}' => visible.push_str("APC"),
+                _ if character.is_control() => visible.push_str(&format!("<U+{:04X}>", character as u32)),
+                _ => visible.push(character),
+            }
+        }
+        visible
+    }
+
+    /// Gets the description of what the sequence does
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+}
+
+impl<'a> Display for EscapeExplanation<'a> {
+
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), Error> {
+        write!(formatter, "{}: {}", self.visible_sequence(), self.description)
+    }
+}
+
+// Explain --------------------------------------------------------------------------------------- /
+
+/// Describes each escape sequence in the given text, in order. Useful for debugging output
+/// that looks wrong in a terminal.
+pub fn explain(text: &str) -> Vec<EscapeExplanation<'_>> {
+    let mut explanations = Vec::new();
+    let mut offset = 0;
+    let mut parser = AnsiParser::new();
+    for segment in ansi_segments(text) {
+        match segment {
+            AnsiSegment::Text(text) => offset += text.len(),
+            AnsiSegment::Escape(sequence) => {
+                let mut description = None;
+                parser.advance(sequence.as_bytes(), |event| {
+                    if description.is_none() {
+                        description = describe_event(&event);
+                    }
+                });
+                if parser.is_pending() {
+                    parser.finish(|_| ());
+                    description = Some(String::from("incomplete escape sequence"));
+                }
+                explanations.push(EscapeExplanation {
+                    offset,
+                    sequence,
+                    description: description.unwrap_or_else(|| String::from("unknown escape sequence")),
+                });
+                offset += sequence.len();
+            }
+        }
+    }
+    explanations
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+fn describe_event(event: &AnsiEvent<'_>) -> Option<String> {
+    let description = match event {
+        AnsiEvent::Style(style) => describe_sgr(style.codes()),
+        AnsiEvent::Csi { params, intermediates, action } => describe_csi(params, intermediates, *action),
+        AnsiEvent::Osc(data) => describe_osc(&String::from_utf8_lossy(data)),
+        AnsiEvent::ControlString { introducer, .. } => String::from(match introducer {
+            b'P' => "device control string",
+            b'X' => "start of string",
+            b'^' => "privacy message",
+            _ => "application program command",
+        }),
+        AnsiEvent::Esc { intermediates, action } => describe_esc(intermediates, *action),
+        AnsiEvent::Invalid(_) => String::from("invalid escape sequence"),
+        AnsiEvent::Text(_) | AnsiEvent::Control(_) => return None,
+    };
+    Some(description)
+}
+
+/// Describes the codes of an SGR sequence, like "bold, foreground Red".
+fn describe_sgr(codes: &[u8]) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    let mut index = 0;
+    while index < codes.len() {
+        let code = codes[index];
+        index += 1;
+        let part = match code {
+            30..=37 | 90..=97 => format!("foreground {}", ANSIForegroundColor::from(code).map(|color| color.to_string()).unwrap_or_default()),
+            40..=47 | 100..=107 => format!("background {}", ANSIBackgroundColor::from(code).map(|color| color.to_string()).unwrap_or_default()),
+            38 | 48 | 58 => {
+                let layer = match code {
+                    38 => "foreground",
+                    48 => "background",
+                    _ => "underline color",
+                };
+                match codes.get(index..) {
+                    Some([5, color, ..]) => {
+                        index += 2;
+                        let (red, green, blue) = ansi256_to_rgb(*color);
+                        format!("{} 256-color #{} (≈ #{:02x}{:02x}{:02x})", layer, color, red, green, blue)
+                    }
+                    Some([2, red, green, blue, ..]) => {
+                        index += 4;
+                        format!("{} RGB #{:02x}{:02x}{:02x}", layer, red, green, blue)
+                    }
+                    _ => {
+                        index = codes.len();
+                        format!("{} (malformed color)", layer)
+                    }
+                }
+            }
+            39 => String::from("default foreground"),
+            49 => String::from("default background"),
+            59 => String::from("default underline color"),
+            7 => String::from("inverse"),
+            8 => String::from("hidden"),
+            21 => String::from("double underline"),
+            22 => String::from("normal intensity"),
+            23 => String::from("not italic"),
+            24 => String::from("not underlined"),
+            25 => String::from("not blinking"),
+            27 => String::from("not inverse"),
+            28 => String::from("not hidden"),
+            29 => String::from("not crossed-out"),
+            _ => SGREffect::from(&code).to_string(),
+        };
+        parts.push(part);
+    }
+    parts.join(", ")
+}
+
+/// Describes a non-SGR CSI sequence, like "cursor up 2".
+fn describe_csi(params: &[u16], intermediates: &[u8], action: char) -> String {
+    let param = |index: usize, default: u16| match params.get(index) {
+        Some(0) | None => default,
+        Some(value) => *value,
+    };
+    if intermediates == b"?" && (action == 'h' || action == 'l') {
+        let verb = if action == 'h' { "enable" } else { "disable" };
+        let modes: Vec<String> = params.iter().map(|mode| match mode {
+            1 => String::from("application cursor keys"),
+            25 => String::from("cursor visibility"),
+            47 | 1047 | 1049 => String::from("alternate screen"),
+            1000 | 1002 | 1003 | 1006 => format!("mouse reporting ({})", mode),
+            2004 => String::from("bracketed paste"),
+            _ => format!("private mode {}", mode),
+        }).collect();
+        return format!("{} {}", verb, modes.join(", "));
+    }
+    match action {
+        'A' => format!("cursor up {}", param(0, 1)),
+        'B' => format!("cursor down {}", param(0, 1)),
+        'C' => format!("cursor forward {}", param(0, 1)),
+        'D' => format!("cursor back {}", param(0, 1)),
+        'E' => format!("cursor to start of line {} down", param(0, 1)),
+        'F' => format!("cursor to start of line {} up", param(0, 1)),
+        'G' => format!("cursor to column {}", param(0, 1)),
+        'H' | 'f' => format!("cursor to row {}, column {}", param(0, 1), param(1, 1)),
+        'J' => String::from(match params.first().copied().unwrap_or(0) {
+            0 => "erase display below cursor",
+            1 => "erase display above cursor",
+            2 => "erase entire display",
+            _ => "erase display and scrollback",
+        }),
+        'K' => String::from(match params.first().copied().unwrap_or(0) {
+            0 => "erase line after cursor",
+            1 => "erase line before cursor",
+            _ => "erase entire line",
+        }),
+        'S' => format!("scroll up {}", param(0, 1)),
+        'T' => format!("scroll down {}", param(0, 1)),
+        's' => String::from("save cursor position"),
+        'u' => String::from("restore cursor position"),
+        'n' => String::from("device status report"),
+        _ => {
+            let params: Vec<String> = params.iter().map(|param| param.to_string()).collect();
+            format!("CSI sequence '{}' (parameters: {})", action, params.join(";"))
+        }
+    }
+}
+
+/// Describes an OSC string, like "set window title to \"Hi\"".
+fn describe_osc(data: &str) -> String {
+    let (command, argument) = match data.find(';') {
+        Some(index) => (&data[..index], &data[index + 1..]),
+        None => (data, ""),
+    };
+    match command {
+        "0" => format!("set window title and icon name to {:?}", argument),
+        "1" => format!("set icon name to {:?}", argument),
+        "2" => format!("set window title to {:?}", argument),
+        "4" => String::from("change palette color"),
+        "7" => format!("set current directory to {:?}", argument),
+        "8" => match argument.find(';').map(|index| &argument[index + 1..]) {
+            Some("") | None => String::from("end hyperlink"),
+            Some(url) => format!("hyperlink to {:?}", url),
+        },
+        "10" => String::from("query or set default foreground color"),
+        "11" => String::from("query or set default background color"),
+        "52" => String::from("write to clipboard"),
+        _ => format!("OSC command {}", command),
+    }
+}
+
+/// Describes a simple escape sequence, like "save cursor".
+fn describe_esc(intermediates: &[u8], action: u8) -> String {
+    match (intermediates, action) {
+        ([], b'7') => String::from("save cursor"),
+        ([], b'8') => String::from("restore cursor"),
+        ([], b'c') => String::from("full reset"),
+        ([], b'D') => String::from("index (line feed)"),
+        ([], b'E') => String::from("next line"),
+        ([], b'M') => String::from("reverse index"),
+        ([], b'=') => String::from("application keypad mode"),
+        ([], b'>') => String::from("normal keypad mode"),
+        ([set @ b'('..=b'+'], charset) => format!("use character set '{}' as G{}", charset as char, set - b'('),
+        _ => format!("escape sequence '{}{}'", String::from_utf8_lossy(intermediates), action as char),
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn descriptions(text: &str) -> Vec<String> {
+        explain(text).iter().map(|explanation| explanation.description().to_string()).collect()
+    }
+
+    #[test]
+    fn test_explain_styles() {
+        assert_eq!(descriptions("\u{001B}[1;93mHi\u{001B}[0m"), vec![
+            "bold, foreground Bright Yellow",
+            "normal/reset",
+        ]);
+        assert_eq!(descriptions("\u{001B}[48;2;255;0;128;3m"), vec!["background RGB #ff0080, italic"]);
+    }
+
+    #[test]
+    fn test_explain_colon_groups() {
+        assert_eq!(descriptions("\u{001B}[38:2::255:0:0m\u{001B}[4:3m\u{001B}[58:5:196;59m"), vec![
+            "foreground RGB #ff0000",
+            "underline",
+            "underline color 256-color #196 (≈ #ff0000), default underline color",
+        ]);
+    }
+
+    #[test]
+    fn test_explain_other_sequences() {
+        let text = "a\u{001B}[2Ab\u{001B}]0;My Title\u{0007}\u{001B}[?25l\u{001B}7\u{001B}[5;10H";
+        assert_eq!(descriptions(text), vec![
+            "cursor up 2",
+            "set window title and icon name to \"My Title\"",
+            "disable cursor visibility",
+            "save cursor",
+            "cursor to row 5, column 10",
+        ]);
+        let explanations = explain(text);
+        assert_eq!(explanations[0].offset(), 1);
+        assert_eq!(explanations[1].visible_sequence(), "ESC]0;My TitleBEL");
+        assert_eq!(descriptions("\u{001B}]0;unterminated"), vec!["incomplete escape sequence"]);
+    }
+}
@@ -0,0 +1,213 @@
+use crate::detect::{ColorLevel, Stream};
+use crate::render::{detected_level, detected_terminfo, write_style, RESET};
+use crate::terminal_style::TerminalStyle;
+use crate::terminfo::Terminfo;
+use std::io::{self, Write};
+use std::panic;
+use std::sync::Once;
+
+// Style Guard ----------------------------------------------------------------------------------- /
+
+/// Writes a style when created and restores the terminal when dropped, so text printed while it's
+/// alive is styled, and the terminal isn't left styled by an early return, `?` or a panic
+/// (guards are dropped while unwinding).
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use terminal_text_styler::{StyleGuard, TerminalStyle};
+///
+/// let mut output = Vec::new();
+/// {
+///     let mut guard = StyleGuard::new(&mut output, &TerminalStyle::red()).unwrap();
+///     write!(guard, "failed").unwrap();
+/// }
+/// assert_eq!(output, b"\x1B[0;31mfailed\x1B[0m");
+/// ```
+#[derive(Debug)]
+pub struct StyleGuard<W: Write> {
+    writer: W,
+    level: ColorLevel,
+    /// The terminal's entry, whose effects are the only ones written (for standard streams)
+    terminfo: Option<&'static Terminfo>,
+    /// The style written when the guard is dropped
+    restore: TerminalStyle,
+}
+
+impl<W: Write> StyleGuard<W> {
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Writes the style, to be reset when the guard is dropped
+    pub fn new(writer: W, style: &TerminalStyle) -> io::Result<Self> {
+        StyleGuard::with_previous(writer, style, &TerminalStyle::reset())
+    }
+
+    /// Writes the style, to be replaced by the previous style when the guard is dropped
+    /// (for guards nested in a styled region)
+    pub fn with_previous(writer: W, style: &TerminalStyle, previous: &TerminalStyle) -> io::Result<Self> {
+        StyleGuard::with_color_level(writer, style, previous, ColorLevel::TrueColor)
+    }
+
+    /// Writes the style for a color level (see `StyledWriter::with_color_level`), to be replaced
+    /// by the previous style when the guard is dropped
+    pub fn with_color_level(writer: W, style: &TerminalStyle, previous: &TerminalStyle, level: ColorLevel) -> io::Result<Self> {
+        StyleGuard::with_terminfo(writer, style, previous, level, None)
+    }
+
+    /// Writes the style for a color level, leaving out effects the terminfo entry (if any) has no
+    /// capability for, to be replaced by the previous style when the guard is dropped
+    fn with_terminfo(mut writer: W, style: &TerminalStyle, previous: &TerminalStyle, level: ColorLevel, terminfo: Option<&'static Terminfo>) -> io::Result<Self> {
+        write_command(&mut writer, style, level, terminfo)?;
+        Ok(StyleGuard { writer, level, terminfo, restore: previous.clone() })
+    }
+
+    // Accessors --------------------------------------------------------------------------------- /
+
+    /// Gets the writer the style is written to
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+}
+
+impl StyleGuard<io::Stdout> {
+
+    /// Writes the style to standard output, if it uses colors (see `stdout`)
+    pub fn stdout(style: &TerminalStyle) -> io::Result<Self> {
+        StyleGuard::with_terminfo(io::stdout(), style, &TerminalStyle::reset(), detected_level(Stream::Stdout), detected_terminfo())
+    }
+}
+
+impl StyleGuard<io::Stderr> {
+
+    /// Writes the style to standard error, if it uses colors (see `stderr`)
+    pub fn stderr(style: &TerminalStyle) -> io::Result<Self> {
+        StyleGuard::with_terminfo(io::stderr(), style, &TerminalStyle::reset(), detected_level(Stream::Stderr), detected_terminfo())
+    }
+}
+
+impl<W: Write> Write for StyleGuard<W> {
+
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write> Drop for StyleGuard<W> {
+
+    /// Writes the previous style (or a reset), including while unwinding from a panic
+    fn drop(&mut self) {
+        let _ = write_command(&mut self.writer, &self.restore, self.level, self.terminfo);
+        let _ = self.writer.flush();
+    }
+}
+
+// Resetting the terminal ------------------------------------------------------------------------ /
+
+/// Resets the style of standard output and standard error (those that use colors), without
+/// waiting for locks other threads may hold on them. Meant for Ctrl-C handlers and other code
+/// that runs while the program is interrupted.
+///
+/// # Examples
+/// ```ignore
+/// // With the `ctrlc` crate
+/// ctrlc::set_handler(|| {
+///     let _ = terminal_text_styler::reset_terminal();
+///     std::process::exit(130);
+/// }).unwrap();
+/// ```
+pub fn reset_terminal() -> io::Result<()> {
+    if detected_level(Stream::Stdout) != ColorLevel::None {
+        write_unlocked(Stream::Stdout, RESET.as_bytes())?;
+    }
+    if detected_level(Stream::Stderr) != ColorLevel::None {
+        write_unlocked(Stream::Stderr, RESET.as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Installs a panic hook that resets the terminal (see `reset_terminal`) before the panic message
+/// is printed, then calls the hook that was installed before. Installing it again does nothing.
+pub fn install_reset_on_panic() {
+    static INSTALL: Once = Once::new();
+    INSTALL.call_once(|| {
+        let previous = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            let _ = reset_terminal();
+            previous(info);
+        }));
+    });
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Writes a style for a color level and terminfo entry (nothing for `ColorLevel::None`)
+fn write_command<W: Write>(writer: &mut W, style: &TerminalStyle, level: ColorLevel, terminfo: Option<&Terminfo>) -> io::Result<()> {
+    let mut command = String::new();
+    // Writing to a `String` can't fail
+    let _ = write_style(&mut command, style.codes(), Some(style.command()), level, terminfo);
+    writer.write_all(command.as_bytes())
+}
+
+/// Writes straight to a stream's file descriptor, so locks on the stream (and its buffer) are skipped
+#[cfg(unix)]
+fn write_unlocked(stream: Stream, bytes: &[u8]) -> io::Result<()> {
+    use std::fs::File;
+    use std::os::unix::io::AsFd;
+    let descriptor = match stream {
+        Stream::Stdout => io::stdout().as_fd().try_clone_to_owned()?,
+        Stream::Stderr => io::stderr().as_fd().try_clone_to_owned()?,
+    };
+    File::from(descriptor).write_all(bytes)
+}
+
+/// Writes to a stream (other platforms have no unlocked way to write to it)
+#[cfg(not(unix))]
+fn write_unlocked(stream: Stream, bytes: &[u8]) -> io::Result<()> {
+    match stream {
+        Stream::Stdout => io::stdout().write_all(bytes),
+        Stream::Stderr => io::stderr().write_all(bytes),
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::panic::AssertUnwindSafe;
+
+    #[test]
+    fn test_restores_on_drop() {
+        let mut output = Vec::new();
+        {
+            let mut outer = StyleGuard::new(&mut output, &TerminalStyle::from(vec![1])).unwrap();
+            write!(outer, "a").unwrap();
+            let mut inner = StyleGuard::with_previous(outer.get_mut(), &TerminalStyle::green(), &TerminalStyle::from(vec![0, 1])).unwrap();
+            write!(inner, "b").unwrap();
+        }
+        assert_eq!(output, b"\x1B[1ma\x1B[0;32mb\x1B[0;1m\x1B[0m");
+        let mut output = Vec::new();
+        drop(StyleGuard::with_color_level(&mut output, &TerminalStyle::from(vec![38, 5, 196]), &TerminalStyle::reset(), ColorLevel::Ansi16));
+        assert_eq!(output, b"\x1B[91m\x1B[0m");
+        let mut output = Vec::new();
+        drop(StyleGuard::with_color_level(&mut output, &TerminalStyle::red(), &TerminalStyle::reset(), ColorLevel::None));
+        assert_eq!(output, b"");
+    }
+
+    #[test]
+    fn test_restores_while_unwinding() {
+        let mut output = Vec::new();
+        let result = panic::catch_unwind(AssertUnwindSafe(|| {
+            let mut guard = StyleGuard::new(&mut output, &TerminalStyle::red()).unwrap();
+            write!(guard, "a").unwrap();
+            panic!("interrupted");
+        }));
+        assert!(result.is_err());
+        assert_eq!(output, b"\x1B[0;31ma\x1B[0m");
+    }
+}
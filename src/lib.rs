@@ -8,6 +8,7 @@
 
 pub use terminal_style::TerminalStyle;
 pub use styled_terminal_text::StyledTerminalText;
+pub use styled_line::StyledLine;
 // Enums
 pub use enums::ansi_foreground::ANSIForegroundColor;
 pub use enums::ansi_background::ANSIBackgroundColor;
@@ -16,11 +17,77 @@ pub use enums::srg_effect::SGREffect;
 pub use utility::*;
 // Traits
 pub use traits::Coded;
+pub use stylize::{Stylize, Styled};
+// Escape sequences
+pub use ansi::{strip_ansi, visible_chunks, ansi_segments, AnsiSegment, AnsiSegments, VisibleChunks};
+// Width
+pub use width::{visible_width, visible_width_with, WidthOptions, AmbiguousWidth};
+// Parsing
+pub use parser::{AnsiParser, AnsiEvent};
+// Sanitizing
+pub use sanitize::{sanitize, SanitizePolicy};
+// Debugging
+pub use explain::{explain, EscapeExplanation};
+pub use notation::{parse_escape_notation, to_escape_notation, EscapeNotation};
+// Colors
+pub use palette::{ansi256_to_rgb, rgb_to_ansi256, ansi256_to_ansi16};
+// Style state
+pub use sgr_state::SGRState;
+pub use optimize::optimize_escapes;
+pub use nesting::repair_nested;
+// Markup
+pub use markup::{parse_markup, escape_markup, Markup, MarkupError};
+// Layout
+pub use wrap::{wrap_styled, wrap_ansi, WrapOptions};
+pub use truncate::{truncate_styled, truncate_ansi, TruncateOptions, TruncatePosition};
+pub use pad::{pad_styled, pad_ansi, PadOptions};
+pub use slice::{slice_styled, slice_styled_with, slice_ansi};
+// Detection
+pub use detect::{color_level, detect_color_level, detect_color_level_with, ColorLevel, Stream};
+pub use terminfo::{Terminfo, TerminfoError};
+// Rendering
+pub use render::{render_policy, set_render_policy, set_thread_render_policy, override_render_policy};
+pub use render::{render_ansi, RenderPolicy, RenderPolicyGuard, ColorMode};
+// Writing
+pub use writer::StyledWriter;
+pub use console::{stdout, stderr, StyledStdout, StyledStderr};
+#[doc(hidden)]
+pub use console::_print_styled;
+pub use guard::{StyleGuard, reset_terminal, install_reset_on_panic};
+// Diff
+pub use diff::{diff, diff_styled, render_diff, DiffOp, DiffOptions, DiffGranularity};
+// Search
+pub use search::{highlight_matches, highlight_matches_ansi, Matcher, FindMatches};
 
 // Modules --------------------------------------------------------------------------------------- /
 
 mod terminal_style;
 mod styled_terminal_text;
+mod styled_line;
 mod enums;
 mod traits;
-mod utility;
\ No newline at end of file
+mod utility;
+mod ansi;
+mod width;
+mod parser;
+mod sanitize;
+mod explain;
+mod notation;
+mod palette;
+mod sgr_state;
+mod optimize;
+mod nesting;
+mod markup;
+mod wrap;
+mod truncate;
+mod pad;
+mod slice;
+mod search;
+mod diff;
+mod stylize;
+mod detect;
+mod terminfo;
+mod render;
+mod writer;
+mod console;
+mod guard;
\ No newline at end of file
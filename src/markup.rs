@@ -0,0 +1,274 @@
+use crate::sgr_state::SGRState;
+use crate::styled_line::StyledLine;
+use crate::styled_terminal_text::StyledTerminalText;
+use crate::terminal_style::TerminalStyle;
+use crate::traits::Coded;
+use crate::{ANSIBackgroundColor, ANSIForegroundColor, SGREffect};
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+
+// Markup Error ---------------------------------------------------------------------------------- /
+
+/// Describes why a markup string couldn't be parsed. Positions are byte offsets into the markup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MarkupError {
+    /// A tag contains a word that is not an effect, color or registered tag
+    UnknownTag { tag: String, position: usize },
+    /// A `[` without a matching `]`
+    UnterminatedTag { position: usize },
+    /// A closing tag without an open tag
+    UnexpectedClose { tag: String, position: usize },
+    /// A closing tag that doesn't match the innermost open tag
+    MismatchedClose { expected: String, found: String, position: usize },
+    /// An open tag that was never closed
+    Unclosed { tag: String, position: usize },
+}
+
+impl Display for MarkupError {
+
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            MarkupError::UnknownTag { tag, position } => write!(formatter, "unknown style '{}' at position {}", tag, position),
+            MarkupError::UnterminatedTag { position } => write!(formatter, "tag at position {} is missing ']' (use '[[' for a literal '[')", position),
+            MarkupError::UnexpectedClose { tag, position } => write!(formatter, "closing tag '[/{}]' at position {} has no open tag", tag, position),
+            MarkupError::MismatchedClose { expected, found, position } => write!(formatter, "closing tag '[/{}]' at position {} doesn't match open tag '[{}]'", found, position, expected),
+            MarkupError::Unclosed { tag, position } => write!(formatter, "tag '[{}]' at position {} is never closed", tag, position),
+        }
+    }
+}
+
+impl Error for MarkupError {}
+
+// Markup ---------------------------------------------------------------------------------------- /
+
+/// Parses inline style markup, like `"[bold red]error[/]: could not open [u]{path}[/u]"`, into a `StyledLine`.
+///
+/// Tags hold one or more words, separated by spaces:
+/// - Effects: `bold`, `faint`, `italic`, `underline`, `slow_blink`, `rapid_blink`, `crossed_out`
+///   (or `b`, `dim`, `i`, `u`, `blink`, `s`)
+/// - Foreground colors: `red`, `bright_yellow`, etc., or `color(183)` for an ANSI 256-color
+/// - Background colors: the same, following `on` (like `on blue`)
+/// - Named tags registered with `register_tag`
+///
+/// `[/]` closes the innermost tag, `[/name]` does the same but checks that the name matches.
+/// Tags can be nested, and `[[` and `]]` produce literal brackets.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{Markup, TerminalStyle};
+///
+/// let mut markup = Markup::new();
+/// markup.register_tag("warning", TerminalStyle::bright_yellow());
+/// let line = markup.parse("[warning]warning[/]: [u]file.txt[/u] [[1 of 2]]").unwrap();
+/// assert_eq!(line.plain(), "warning: file.txt [1 of 2]");
+/// assert_eq!(line.spans()[0].style(), &TerminalStyle::from(vec![0, 1, 93]));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Markup {
+    tags: HashMap<String, TerminalStyle>,
+}
+
+impl Markup {
+
+    /// Creates a markup parser without any named tags
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers a named tag (like `[warning]`) for a style. Returns the style previously registered
+    /// with this name, if any.
+    pub fn register_tag(&mut self, name: &str, style: TerminalStyle) -> Option<TerminalStyle> {
+        self.tags.insert(normalize(name), style)
+    }
+
+    /// Parses the markup into styled text
+    pub fn parse(&self, markup: &str) -> Result<StyledLine, MarkupError> {
+        let mut line = StyledLine::new();
+        // Open tags with their position and the resulting style
+        let mut open: Vec<(&str, usize, SGRState)> = Vec::new();
+        let mut text = String::new();
+        let mut index = 0;
+        while index < markup.len() {
+            let rest = &markup[index..];
+            if rest.starts_with("[[") || rest.starts_with("]]") {
+                text.push_str(&rest[..1]);
+                index += 2;
+                continue;
+            }
+            if !rest.starts_with('[') {
+                let character = rest.chars().next().unwrap_or_default();
+                text.push(character);
+                index += character.len_utf8();
+                continue;
+            }
+            let end = rest.find(']').ok_or(MarkupError::UnterminatedTag { position: index })?;
+            let tag = rest[1..end].trim();
+            let current = open.last().map(|(_, _, state)| state.clone()).unwrap_or_default();
+            Self::flush(&mut line, &mut text, &current);
+            if let Some(name) = tag.strip_prefix('/') {
+                let name = name.trim();
+                let (expected, _, _) = open.pop()
+                    .ok_or_else(|| MarkupError::UnexpectedClose { tag: name.to_string(), position: index })?;
+                if !name.is_empty() && normalize(name) != normalize(expected) {
+                    return Err(MarkupError::MismatchedClose {
+                        expected: expected.to_string(),
+                        found: name.to_string(),
+                        position: index,
+                    });
+                }
+            } else {
+                let mut state = current;
+                state.apply(&self.codes(tag, index)?);
+                open.push((tag, index, state));
+            }
+            index += end + 1;
+        }
+        if let Some((tag, position, _)) = open.pop() {
+            return Err(MarkupError::Unclosed { tag: tag.to_string(), position });
+        }
+        Self::flush(&mut line, &mut text, &SGRState::new());
+        Ok(line)
+    }
+
+    // Private instance methods ------------------------------------------------------------------ /
+
+    /// Adds the collected text (if any) to the line
+    fn flush(line: &mut StyledLine, text: &mut String, state: &SGRState) {
+        if !text.is_empty() {
+            line.push(StyledTerminalText::new(text, state.to_style()));
+            text.clear();
+        }
+    }
+
+    /// Gets the SGR codes for the words of a tag
+    fn codes(&self, tag: &str, position: usize) -> Result<Vec<u8>, MarkupError> {
+        let unknown = |word: &str| MarkupError::UnknownTag { tag: word.to_string(), position };
+        if let Some(style) = self.tags.get(&normalize(tag)) {
+            return Ok(style.codes().clone());
+        }
+        let mut codes = Vec::new();
+        let mut words = tag.split_whitespace();
+        if tag.is_empty() {
+            return Err(unknown(tag));
+        }
+        while let Some(word) = words.next() {
+            let name = normalize(word);
+            if let Some(style) = self.tags.get(&name) {
+                codes.extend_from_slice(style.codes());
+            } else if name == "on" {
+                let color = words.next().ok_or_else(|| unknown(word))?;
+                let color = foreground_color(&normalize(color)).ok_or_else(|| unknown(color))?;
+                let background = match color {
+                    ANSIForegroundColor::ANSI256(index) => Some(ANSIBackgroundColor::ANSI256(index)),
+                    basic => ANSIBackgroundColor::from(basic.code() + 10),
+                };
+                let background = background.ok_or_else(|| unknown(word))?;
+                codes.push(background.code());
+                if let Some((mode, index)) = background.additional_codes() {
+                    codes.extend_from_slice(&[mode, index]);
+                }
+            } else if let Some(color) = foreground_color(&name) {
+                codes.push(color.code());
+                if let Some((mode, index)) = color.additional_codes() {
+                    codes.extend_from_slice(&[mode, index]);
+                }
+            } else if let Some(effect) = effect(&name) {
+                codes.push(effect.code());
+            } else {
+                return Err(unknown(word));
+            }
+        }
+        Ok(codes)
+    }
+}
+
+/// Parses inline style markup (without named tags). See `Markup` for the syntax.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::parse_markup;
+///
+/// let line = parse_markup("[bold red]error[/]: could not open [u]foo.rs[/u]").unwrap();
+/// assert_eq!(line.plain(), "error: could not open foo.rs");
+/// assert!(parse_markup("[bold]unclosed").is_err());
+/// ```
+pub fn parse_markup(markup: &str) -> Result<StyledLine, MarkupError> {
+    Markup::new().parse(markup)
+}
+
+/// Escapes brackets in text, so it can be placed in markup without being read as tags.
+pub fn escape_markup(text: &str) -> String {
+    text.replace('[', "[[").replace(']', "]]")
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Lowercases a name and uses `_` between words (so `Bright Yellow` and `bright-yellow` become `bright_yellow`)
+fn normalize(name: &str) -> String {
+    name.trim().to_lowercase().replace([' ', '-'], "_")
+}
+
+fn effect(name: &str) -> Option<SGREffect> {
+    let effect = match name {
+        "b" => SGREffect::Bold,
+        "dim" => SGREffect::Faint,
+        "i" => SGREffect::Italic,
+        "u" => SGREffect::Underline,
+        "blink" => SGREffect::SlowBlink,
+        "s" | "strike" => SGREffect::CrossedOut,
+        _ => {
+            let effects = vec![
+                SGREffect::Bold, SGREffect::Faint, SGREffect::Italic, SGREffect::Underline,
+                SGREffect::SlowBlink, SGREffect::RapidBlink, SGREffect::CrossedOut,
+            ];
+            return effects.into_iter().find(|effect| normalize(&effect.to_string()) == name);
+        }
+    };
+    Some(effect)
+}
+
+fn foreground_color(name: &str) -> Option<ANSIForegroundColor> {
+    if let Some(index) = name.strip_prefix("color(").and_then(|rest| rest.strip_suffix(')')) {
+        return index.parse().ok().map(ANSIForegroundColor::ANSI256);
+    }
+    (30..=37).chain(90..=97)
+        .filter_map(ANSIForegroundColor::from)
+        .find(|color| normalize(&color.to_string()) == name)
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_markup() {
+        let line = parse_markup("a [bold red on blue]b [i]c[/i][/] [color(183) on color(17)]d[/]").unwrap();
+        let styles: Vec<Vec<u8>> = line.spans().iter().map(|span| span.style().codes().clone()).collect();
+        assert_eq!(styles, vec![
+            vec![0],
+            vec![0, 1, 31, 44],
+            vec![0, 1, 3, 31, 44],
+            vec![0],
+            vec![0, 38, 5, 183, 48, 5, 17],
+        ]);
+        assert_eq!(line.plain(), "a b c d");
+        assert_eq!(parse_markup(&escape_markup("[x]")).unwrap().plain(), "[x]");
+    }
+
+    #[test]
+    fn test_markup_errors() {
+        assert_eq!(parse_markup("[bold purple]x[/]"), Err(MarkupError::UnknownTag { tag: String::from("purple"), position: 0 }));
+        assert_eq!(parse_markup("ab[bold"), Err(MarkupError::UnterminatedTag { position: 2 }));
+        assert_eq!(parse_markup("x[/]"), Err(MarkupError::UnexpectedClose { tag: String::new(), position: 1 }));
+        assert_eq!(
+            parse_markup("[b][i]x[/b][/i]"),
+            Err(MarkupError::MismatchedClose { expected: String::from("i"), found: String::from("b"), position: 7 })
+        );
+        let error = parse_markup("[b]x").unwrap_err();
+        assert_eq!(error, MarkupError::Unclosed { tag: String::from("b"), position: 0 });
+        assert_eq!(error.to_string(), "tag '[b]' at position 0 is never closed");
+    }
+}
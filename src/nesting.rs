@@ -0,0 +1,213 @@
+use crate::ansi::{ansi_segments, sgr_codes, AnsiSegment};
+use crate::sgr_state::SGRState;
+use crate::terminal_style::TerminalStyle;
+
+// Nesting --------------------------------------------------------------------------------------- /
+
+/// Repairs text containing nested styles (like the output of `wrap` placed inside another `wrap`).
+/// SGR sequences that open a nested style start a new level, and each reset (`ESC[0m`) closes the
+/// innermost level, restoring the enclosing style instead of resetting to the default. Closing the
+/// outermost level resets the terminal.
+///
+/// A sequence only opens a level when a later reset is left to close it; other sequences (like the
+/// `ESC[22m` of a partial reset, or the changes kept by `optimize_escapes`) change the style of
+/// the level they're in. Sequences that restore the enclosing style (like the ones `wrap_nested`
+/// writes in place of resets) close a level, so repaired text can be nested again.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{repair_nested, TerminalStyle};
+///
+/// let bold = TerminalStyle::from(vec![1]);
+/// let broken = TerminalStyle::red().wrap(&format!("a {} b", bold.wrap("x")));
+/// assert_eq!(
+///     repair_nested(&broken),
+///     "\u{001B}[0;31ma \u{001B}[1mx\u{001B}[0;31m b\u{001B}[0m"
+/// );
+/// ```
+pub fn repair_nested(text: &str) -> String {
+    let segments: Vec<AnsiSegment> = ansi_segments(text).collect();
+    // The codes of each SGR sequence that sets or resets a style
+    let codes: Vec<Option<Vec<u8>>> = segments.iter()
+        .map(|segment| match segment {
+            AnsiSegment::Escape(escape) => sgr_codes(escape).filter(|codes| !codes.is_empty()),
+            AnsiSegment::Text(_) => None,
+        })
+        .collect();
+    let closes = closes(&codes.iter().flatten().collect::<Vec<_>>());
+    let mut roles = roles(&closes).into_iter();
+    let mut repaired = String::with_capacity(text.len());
+    let mut stack: Vec<SGRState> = vec![SGRState::new()];
+    for (segment, codes) in segments.iter().zip(codes.iter()) {
+        let (escape, codes) = match (segment, codes) {
+            (AnsiSegment::Escape(escape), Some(codes)) => (escape, codes),
+            (AnsiSegment::Escape(text), None) | (AnsiSegment::Text(text), _) => {
+                repaired.push_str(text);
+                continue;
+            }
+        };
+        match roles.next() {
+            Some(Role::Close) => {
+                if stack.len() > 1 {
+                    stack.pop();
+                } else {
+                    stack[0] = SGRState::new();
+                }
+                let restored = stack.last().map(SGRState::to_style).unwrap_or_else(TerminalStyle::reset);
+                repaired.push_str(restored.command());
+            }
+            Some(Role::Open) => {
+                let mut state = stack.last().cloned().unwrap_or_default();
+                state.apply(codes);
+                stack.push(state);
+                repaired.push_str(escape);
+            }
+            _ => {
+                if let Some(state) = stack.last_mut() {
+                    state.apply(codes);
+                }
+                repaired.push_str(escape);
+            }
+        }
+    }
+    repaired
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// What an SGR sequence does to the levels of nesting
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Role {
+    /// Starts a nested level
+    Open,
+    /// Changes the style of the current level
+    Change,
+    /// Ends the current level (a reset, or a sequence restoring the enclosing style)
+    Close,
+}
+
+/// Finds which SGR sequences close a level: resets, and sequences starting with a reset that set
+/// the style of the enclosing level (as long as the later resets still have levels to close, since
+/// the same sequence could open a level too).
+fn closes(codes: &[&Vec<u8>]) -> Vec<bool> {
+    let mut resets_after = codes.iter().filter(|codes| is_reset(codes)).count();
+    // Every sequence that sets a style is taken to open a level here, to know what a sequence restores
+    let mut stack: Vec<SGRState> = vec![SGRState::new()];
+    let mut closes = Vec::with_capacity(codes.len());
+    for codes in codes {
+        if is_reset(codes) {
+            resets_after -= 1;
+            if stack.len() > 1 {
+                stack.pop();
+            }
+            closes.push(true);
+            continue;
+        }
+        let mut state = stack.last().cloned().unwrap_or_default();
+        state.apply(codes);
+        let depth = stack.len() - 1;
+        let restores = codes[0] == 0 && depth >= 2 && state == stack[depth - 1] && depth > resets_after;
+        if restores {
+            stack.pop();
+        } else {
+            stack.push(state);
+        }
+        closes.push(restores);
+    }
+    closes
+}
+
+/// Finds the role of each SGR sequence, given whether each one closes a level. Levels are only
+/// opened when a later close would otherwise have no level to close, and then by the earliest
+/// sequence of the level they're opened in.
+fn roles(closes: &[bool]) -> Vec<Role> {
+    // How many levels must be open before each sequence, for every later close to close one
+    // (counting every later sequence that sets a style as one that could open a level)
+    let mut needed = vec![0usize; closes.len() + 1];
+    for (index, closes) in closes.iter().enumerate().rev() {
+        needed[index] = if *closes { needed[index + 1] + 1 } else { needed[index + 1].saturating_sub(1) };
+    }
+    let mut roles = Vec::with_capacity(closes.len());
+    let mut depth = 0usize;
+    for (index, closes) in closes.iter().enumerate() {
+        if *closes {
+            depth = depth.saturating_sub(1);
+            roles.push(Role::Close);
+        } else if needed[index + 1] > depth {
+            depth += 1;
+            // The level starts at the first of the changes made in the level it's opened in
+            let first = roles.iter().rposition(|role| *role != Role::Change).map_or(0, |previous| previous + 1);
+            roles.push(Role::Change);
+            roles[first] = Role::Open;
+        } else {
+            roles.push(Role::Change);
+        }
+    }
+    roles
+}
+
+/// Whether the codes do nothing but reset
+fn is_reset(codes: &[u8]) -> bool {
+    codes.iter().all(|code| *code == 0)
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::optimize::optimize_escapes;
+
+    #[test]
+    fn test_repair_nested() {
+        assert_eq!(repair_nested("plain"), "plain");
+        let blue = TerminalStyle::blue();
+        let bold = TerminalStyle::from(vec![1]);
+        let inner = format!("b {} b", bold.wrap("c"));
+        let text = TerminalStyle::red().wrap(&format!("a {} a", blue.wrap(&inner)));
+        assert_eq!(
+            repair_nested(&text),
+            "\u{001B}[0;31ma \u{001B}[0;34mb \u{001B}[1mc\u{001B}[0;34m b\u{001B}[0;31m a\u{001B}[0m"
+        );
+        // Extra resets never go below the default
+        assert_eq!(repair_nested("\u{001B}[0m\u{001B}[0mx"), "\u{001B}[0m\u{001B}[0mx");
+    }
+
+    #[test]
+    fn test_nested_composition() {
+        let red = TerminalStyle::red();
+        let blue = TerminalStyle::blue();
+        let bold = TerminalStyle::from(vec![1]);
+        let inner = blue.wrap_nested(&format!("p {} q", bold.wrap("y")));
+        assert_eq!(
+            red.wrap_nested(&format!("a {} b {} c", bold.wrap("x"), inner)),
+            "\u{001B}[0;31ma \u{001B}[1mx\u{001B}[0;31m b \u{001B}[0;34mp \u{001B}[1my\u{001B}[0;34m q\u{001B}[0;31m c\u{001B}[0m"
+        );
+        // The inner restore of red is an opener here, since both later resets need a level to close
+        let inner = blue.wrap_nested(&format!("{} z", red.wrap("q")));
+        assert_eq!(
+            red.wrap_nested(&format!("{} w", inner)),
+            "\u{001B}[0;31m\u{001B}[0;34m\u{001B}[0;31mq\u{001B}[0;34m z\u{001B}[0;31m w\u{001B}[0m"
+        );
+    }
+
+    #[test]
+    fn test_partial_resets() {
+        let text = "\u{001B}[1mbold\u{001B}[22m normal\u{001B}[0m tail";
+        assert_eq!(repair_nested(text), text);
+        let inner = "\u{001B}[31ma\u{001B}[39m b\u{001B}[0m";
+        assert_eq!(
+            TerminalStyle::blue().wrap_nested(&format!("x {} y", inner)),
+            "\u{001B}[0;34mx \u{001B}[31ma\u{001B}[39m b\u{001B}[0;34m y\u{001B}[0m"
+        );
+    }
+
+    #[test]
+    fn test_optimized_input() {
+        let optimized = optimize_escapes(&format!("{}{}", TerminalStyle::red().wrap("a"), TerminalStyle::bright_red().wrap("b")));
+        let repaired = TerminalStyle::blue().wrap_nested(&format!("x {} y", optimized));
+        assert_eq!(repaired, "\u{001B}[0;34mx \u{001B}[31ma\u{001B}[1;91mb\u{001B}[0;34m y\u{001B}[0m");
+        let sequential = format!("{} {}", optimized, TerminalStyle::green().wrap("c"));
+        assert_eq!(repair_nested(&sequential), sequential);
+    }
+}
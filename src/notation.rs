@@ -0,0 +1,177 @@
+use std::borrow::Cow;
+
+// Escape Notation ------------------------------------------------------------------------------- /
+
+/// Ways of writing an escape character as literal text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EscapeNotation {
+    /// `\e` (as used by `printf` and `echo -e`)
+    Short,
+    /// `\033` (octal, as often found in shell scripts), or `\0033` when a digit follows
+    Octal,
+    /// `\x1b` (hexadecimal)
+    Hex,
+    /// `\u{1b}` (as used in Rust strings)
+    Unicode,
+}
+
+// Conversion ------------------------------------------------------------------------------------ /
+
+/// Turns literal escape notation (like `\033[1;31m`, `\e[0m`, `\x1b[...` or `\u{1b}[...`) into real
+/// escape characters. Also understands `\a` and `\007` (bell, used to end OSC sequences) and `\\`
+/// (a literal backslash). Any other backslash is kept as it is.
+/// Returns the original text (without copying) if it contains no backslashes.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{parse_escape_notation, TerminalStyle};
+///
+/// assert_eq!(parse_escape_notation(r"\033[0;31mred\e[0m"), TerminalStyle::red().wrap("red"));
+/// ```
+pub fn parse_escape_notation(text: &str) -> Cow<'_, str> {
+    if !text.contains('\\') {
+        return Cow::Borrowed(text);
+    }
+    let mut parsed = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(index) = rest.find('\\') {
+        parsed.push_str(&rest[..index]);
+        rest = &rest[index..];
+        match parse_escape(rest) {
+            Some((character, length)) => {
+                parsed.push(character);
+                rest = &rest[length..];
+            }
+            None => {
+                parsed.push('\\');
+                rest = &rest[1..];
+            }
+        }
+    }
+    parsed.push_str(rest);
+    Cow::Owned(parsed)
+}
+
+/// Makes escape sequences visible by writing control characters in the given notation
+/// (backslashes are doubled, so `parse_escape_notation` turns the result back into the original).
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{to_escape_notation, EscapeNotation, TerminalStyle};
+///
+/// let red = TerminalStyle::red().wrap("red");
+/// assert_eq!(to_escape_notation(&red, EscapeNotation::Short), r"\e[0;31mred\e[0m");
+/// assert_eq!(to_escape_notation(&red, EscapeNotation::Octal), r"\033[0;31mred\033[0m");
+/// ```
+pub fn to_escape_notation(text: &str, notation: EscapeNotation) -> String {
+    let mut visible = String::with_capacity(text.len() + 8);
+    let mut characters = text.chars().peekable();
+    while let Some(character) = characters.next() {
+        match character {
+            '\\' => visible.push_str(r"\\"),
+            '\u{1B}' if notation == EscapeNotation::Short => visible.push_str(r"\e"),
+            '\u{07}' if notation == EscapeNotation::Short => visible.push_str(r"\a"),
+            '\n' | '\t' => visible.push(character),
+            _ if character.is_control() => match notation {
+                // Before another octal digit, `\0NNN` keeps the digit from being read as part of the code
+                EscapeNotation::Octal if characters.peek().is_some_and(|next| ('0'..='7').contains(next)) => {
+                    visible.push_str(&format!("\\0{:03o}", character as u32))
+                }
+                EscapeNotation::Octal => visible.push_str(&format!("\\{:03o}", character as u32)),
+                EscapeNotation::Unicode => visible.push_str(&format!("\\u{{{:x}}}", character as u32)),
+                EscapeNotation::Short | EscapeNotation::Hex => visible.push_str(&format!("\\x{:02x}", character as u32)),
+            },
+            _ => visible.push(character),
+        }
+    }
+    visible
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Parses a single escape at the start of the text (which starts with a backslash),
+/// returning the character and the length of its notation.
+fn parse_escape(text: &str) -> Option<(char, usize)> {
+    let bytes = text.as_bytes();
+    match bytes.get(1)? {
+        b'\\' => Some(('\\', 2)),
+        b'e' | b'E' => Some(('\u{1B}', 2)),
+        b'a' => Some(('\u{07}', 2)),
+        b'0'..=b'7' => {
+            // Up to three octal digits, after an optional leading zero (`\033` or `\0033`)
+            let digits = &text[1..];
+            let has_leading_zero = digits.starts_with('0') && digits.get(1..4).is_some_and(is_octal);
+            let digits = if has_leading_zero { &digits[1..] } else { digits };
+            let length = digits.bytes().take(3).take_while(|byte| (b'0'..=b'7').contains(byte)).count();
+            let code = u32::from_str_radix(&digits[..length], 8).ok()?;
+            Some((std::char::from_u32(code)?, text.len() - digits.len() + length))
+        }
+        b'x' => {
+            let length = bytes[2..].iter().take(2).take_while(|byte| byte.is_ascii_hexdigit()).count();
+            if length == 0 {
+                return None;
+            }
+            let code = u32::from_str_radix(&text[2..2 + length], 16).ok()?;
+            Some((std::char::from_u32(code)?, 2 + length))
+        }
+        b'u' if bytes.get(2) == Some(&b'{') => {
+            let end = text.find('}')?;
+            let code = u32::from_str_radix(&text[3..end], 16).ok()?;
+            Some((std::char::from_u32(code)?, end + 1))
+        }
+        b'u' => {
+            let digits = text.get(2..6)?;
+            if !digits.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+                return None;
+            }
+            let code = u32::from_str_radix(digits, 16).ok()?;
+            Some((std::char::from_u32(code)?, 6))
+        }
+        _ => None,
+    }
+}
+
+fn is_octal(text: &str) -> bool {
+    text.bytes().all(|byte| (b'0'..=b'7').contains(&byte))
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_escape_notation() {
+        let expected = "\u{001B}[1;31mred\u{001B}[0m";
+        assert_eq!(parse_escape_notation(r"\033[1;31mred\033[0m"), expected);
+        assert_eq!(parse_escape_notation(r"\0033[1;31mred\E[0m"), expected);
+        assert_eq!(parse_escape_notation(r"\x1b[1;31mred\x1B[0m"), expected);
+        assert_eq!(parse_escape_notation(r"\u001b[1;31mred\u{1b}[0m"), expected);
+        assert_eq!(parse_escape_notation(r"\e]0;title\a"), "\u{001B}]0;title\u{0007}");
+        assert_eq!(parse_escape_notation(r"C:\path\\to\q"), r"C:\path\to\q");
+        assert!(matches!(parse_escape_notation("no escapes"), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let original = "a\\e \u{001B}[31mb\u{0007}\u{0001}c";
+        for notation in [EscapeNotation::Short, EscapeNotation::Octal, EscapeNotation::Hex, EscapeNotation::Unicode].iter() {
+            let visible = to_escape_notation(original, *notation);
+            assert!(!visible.contains('\u{001B}'));
+            assert_eq!(parse_escape_notation(&visible), original);
+        }
+        assert_eq!(to_escape_notation("\u{001B}[0m\u{0001}", EscapeNotation::Unicode), r"\u{1b}[0m\u{1}");
+    }
+
+    #[test]
+    fn test_round_trip_before_digits() {
+        let originals = ["\u{001B}7saved\u{001B}8", "\u{0001}7", "\u{001B}0\u{001B}", "\u{0007}12"];
+        for original in originals.iter() {
+            for notation in [EscapeNotation::Short, EscapeNotation::Octal, EscapeNotation::Hex, EscapeNotation::Unicode].iter() {
+                assert_eq!(parse_escape_notation(&to_escape_notation(original, *notation)), *original);
+            }
+        }
+        assert_eq!(to_escape_notation("\u{001B}7\u{001B}[0m", EscapeNotation::Octal), r"\00337\033[0m");
+    }
+}
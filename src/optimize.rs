@@ -0,0 +1,82 @@
+use crate::ansi::{ansi_segments, sgr_codes, AnsiSegment};
+use crate::sgr_state::SGRState;
+
+// Optimize -------------------------------------------------------------------------------------- /
+
+/// Re-encodes an already styled string with the fewest escape codes needed for the same visual result.
+/// Adjacent text with the same style is merged, resets without effect are dropped and style
+/// changes only switch what is different. Other escape sequences are kept in place.
+/// The text is assumed to start with the terminal in its default style, and ends in the same
+/// style as the original.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{optimize_escapes, TerminalStyle};
+///
+/// let red = TerminalStyle::red();
+/// let churn = format!("{}{}{}", red.wrap("a"), red.wrap("b"), TerminalStyle::bright_red().wrap("c"));
+/// assert_eq!(optimize_escapes(&churn), "\u{001B}[31mab\u{001B}[1;91mc\u{001B}[0m");
+/// ```
+pub fn optimize_escapes(text: &str) -> String {
+    let mut output = String::with_capacity(text.len());
+    let mut current = SGRState::new();
+    let mut desired = SGRState::new();
+    for segment in ansi_segments(text) {
+        match segment {
+            AnsiSegment::Escape(escape) => match sgr_codes(escape) {
+                Some(codes) => desired.apply(&codes),
+                None => {
+                    // Some sequences (like erasing) depend on the current style, so switch first
+                    switch_style(&mut output, &mut current, &desired);
+                    output.push_str(escape);
+                }
+            },
+            AnsiSegment::Text(text) => {
+                switch_style(&mut output, &mut current, &desired);
+                output.push_str(text);
+            }
+        }
+    }
+    switch_style(&mut output, &mut current, &desired);
+    output
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Writes the shortest escape code that switches from the current to the desired style (if needed)
+fn switch_style(output: &mut String, current: &mut SGRState, desired: &SGRState) {
+    if let Some(style) = current.transition_to(desired) {
+        output.push_str(style.command());
+        *current = desired.clone();
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terminal_style::TerminalStyle;
+
+    #[test]
+    fn test_optimize_escapes() {
+        assert_eq!(optimize_escapes("plain"), "plain");
+        assert_eq!(optimize_escapes("\u{001B}[0m\u{001B}[0mplain\u{001B}[0m"), "plain");
+        let bold = TerminalStyle::new(vec![crate::SGREffect::Bold], None, None);
+        let text = format!("{} {}", bold.wrap("a"), TerminalStyle::red().wrap("b"));
+        assert_eq!(optimize_escapes(&text), "\u{001B}[1ma\u{001B}[0m \u{001B}[31mb\u{001B}[0m");
+        let unterminated = "\u{001B}[31ma\u{001B}[0;31mb";
+        assert_eq!(optimize_escapes(unterminated), "\u{001B}[31mab");
+        let erase = "\u{001B}[41m\u{001B}[K\u{001B}[0m";
+        assert_eq!(optimize_escapes(erase), erase);
+    }
+
+    #[test]
+    fn test_underline_colors() {
+        let indexed = "\u{001B}[58;5;196mx\u{001B}[0m";
+        assert_eq!(optimize_escapes(indexed), indexed);
+        let rgb = "\u{001B}[4;58;2;255;0;0mx\u{001B}[0m";
+        assert_eq!(optimize_escapes(rgb), rgb);
+        assert_eq!(optimize_escapes("\u{001B}[4;58;5;1mx\u{001B}[59my\u{001B}[0m"), "\u{001B}[4;58;5;1mx\u{001B}[59my\u{001B}[0m");
+    }
+}
@@ -0,0 +1,177 @@
+use crate::sgr_state::SGRState;
+use crate::styled_line::StyledLine;
+use crate::styled_terminal_text::StyledTerminalText;
+use crate::terminal_style::TerminalStyle;
+use crate::truncate::{truncate_styled, TruncateOptions};
+use crate::width::WidthOptions;
+use std::fmt::{Alignment, Formatter};
+use std::iter;
+
+// Pad Options ----------------------------------------------------------------------------------- /
+
+/// Options used to pad styled text to a given width.
+///
+/// # Examples
+/// ```
+/// use std::fmt::Alignment;
+/// use terminal_text_styler::PadOptions;
+///
+/// let options = PadOptions { fill: '.', ..PadOptions::new(20, Alignment::Right) };
+/// assert_eq!(options.width, 20);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PadOptions {
+    /// Minimum number of columns of the padded text
+    pub width: usize,
+    /// Where the text is placed within the width
+    pub alignment: Alignment,
+    /// Character used for padding (a space by default)
+    pub fill: char,
+    /// Whether the padding takes the background color of the text next to it
+    pub extend_background: bool,
+    /// Options used to measure the width of text
+    pub width_options: WidthOptions,
+}
+
+impl PadOptions {
+
+    /// Creates options for the given width and alignment, padding with un-styled spaces
+    pub fn new(width: usize, alignment: Alignment) -> Self {
+        PadOptions {
+            width,
+            alignment,
+            fill: ' ',
+            extend_background: false,
+            width_options: WidthOptions::default(),
+        }
+    }
+}
+
+// Pad ------------------------------------------------------------------------------------------- /
+
+/// Pads styled text with the fill character until it is at least the given width, measured in
+/// terminal columns (so escape codes and wide characters are counted correctly).
+/// Text that is already as wide is returned as it is.
+///
+/// # Examples
+/// ```
+/// use std::fmt::Alignment;
+/// use terminal_text_styler::{pad_styled, PadOptions, StyledLine, TerminalStyle};
+///
+/// let line = StyledLine::parse(&TerminalStyle::from(vec![44]).wrap("ok"));
+/// let options = PadOptions { extend_background: true, ..PadOptions::new(6, Alignment::Center) };
+/// assert_eq!(pad_styled(&line, &options).output(), "\u{001B}[0;44m  ok  \u{001B}[0m");
+/// ```
+pub fn pad_styled(line: &StyledLine, options: &PadOptions) -> StyledLine {
+    let width = line.visible_width_with(&options.width_options);
+    if width >= options.width {
+        return line.clone();
+    }
+    let padding = options.width - width;
+    let (left, right) = match options.alignment {
+        Alignment::Left => (0, padding),
+        Alignment::Right => (padding, 0),
+        Alignment::Center => (padding / 2, padding - padding / 2),
+    };
+    let mut padded = StyledLine::new();
+    let mut spans = line.spans().iter().filter(|span| !span.plain().is_empty());
+    if left > 0 {
+        padded.push(fill(left, options, spans.clone().next()));
+    }
+    padded.extend(line.spans().iter().cloned());
+    if right > 0 {
+        padded.push(fill(right, options, spans.next_back()));
+    }
+    padded
+}
+
+/// Pads text containing SGR escape codes to the given width. See `pad_styled`.
+///
+/// # Examples
+/// ```
+/// use std::fmt::Alignment;
+/// use terminal_text_styler::{pad_ansi, PadOptions, highlight_red};
+///
+/// let padded = pad_ansi(highlight_red("日本").output(), &PadOptions::new(6, Alignment::Right));
+/// assert_eq!(padded, "  \u{001B}[0;31m日本\u{001B}[0m");
+/// ```
+pub fn pad_ansi(text: &str, options: &PadOptions) -> String {
+    pad_styled(&StyledLine::parse(text), options).output()
+}
+
+/// Applies the width, fill, alignment and precision of a formatter to a line, like `format!`
+/// does for strings. Precision truncates the line (without an ellipsis) and text is aligned to
+/// the left by default. Returns `None` if the formatter has neither width nor precision.
+pub(crate) fn format_line(line: &StyledLine, formatter: &Formatter<'_>) -> Option<StyledLine> {
+    if formatter.width().is_none() && formatter.precision().is_none() {
+        return None;
+    }
+    let mut formatted = line.clone();
+    if let Some(precision) = formatter.precision() {
+        let options = TruncateOptions {
+            ellipsis: StyledTerminalText::new("", TerminalStyle::no_color()),
+            ..TruncateOptions::new(precision)
+        };
+        formatted = truncate_styled(&formatted, &options);
+    }
+    if let Some(width) = formatter.width() {
+        let options = PadOptions {
+            fill: formatter.fill(),
+            ..PadOptions::new(width, formatter.align().unwrap_or(Alignment::Left))
+        };
+        formatted = pad_styled(&formatted, &options);
+    }
+    Some(formatted)
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Makes padding of the given number of columns, next to the given span
+fn fill(columns: usize, options: &PadOptions, next_to: Option<&StyledTerminalText>) -> StyledTerminalText {
+    let (fill, fill_width) = match options.width_options.grapheme_width(options.fill.encode_utf8(&mut [0; 4])) {
+        0 => (' ', 1),
+        width => (options.fill, width),
+    };
+    // Columns a wide fill character can't cover are filled with spaces
+    let text: String = iter::repeat_n(fill, columns / fill_width)
+        .chain(iter::repeat_n(' ', columns % fill_width))
+        .collect();
+    let style = match next_to {
+        Some(span) if options.extend_background => SGRState::from_style(span.style()).background_only().to_style(),
+        _ => TerminalStyle::no_color(),
+    };
+    StyledTerminalText::new(&text, style)
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pad_styled() {
+        let mut line = StyledLine::new();
+        line.push_str("a");
+        line.push_styled("b", TerminalStyle::from(vec![1, 31, 42]));
+        let padded = |options: PadOptions| pad_styled(&line, &options).output();
+        assert_eq!(padded(PadOptions::new(1, Alignment::Right)), line.output());
+        assert_eq!(padded(PadOptions::new(5, Alignment::Left)), "a\u{001B}[1;31;42mb\u{001B}[0m   ");
+        assert_eq!(padded(PadOptions::new(5, Alignment::Center)), " a\u{001B}[1;31;42mb\u{001B}[0m  ");
+        let options = PadOptions { extend_background: true, fill: '-', ..PadOptions::new(5, Alignment::Center) };
+        assert_eq!(padded(options), "-a\u{001B}[1;31;42mb\u{001B}[0;42m--\u{001B}[0m");
+        let options = PadOptions { fill: '日', ..PadOptions::new(7, Alignment::Right) };
+        assert_eq!(pad_styled(&StyledLine::from("ab"), &options).plain(), "日日 ab");
+    }
+
+    #[test]
+    fn test_format_specifiers() {
+        let text = StyledTerminalText::new("日本語", TerminalStyle::red());
+        assert_eq!(format!("{}", text), "\u{001B}[0;31m日本語\u{001B}[0m");
+        assert_eq!(format!("[{:8}]", text), "[\u{001B}[0;31m日本語\u{001B}[0m  ]");
+        assert_eq!(format!("[{:*>8}]", text), "[**\u{001B}[0;31m日本語\u{001B}[0m]");
+        assert_eq!(format!("[{:^9.4}]", text), "[  \u{001B}[0;31m日本\u{001B}[0m   ]");
+        let line = StyledLine::from(text);
+        assert_eq!(format!("{:>8}", line), format!("  {}", line));
+    }
+}
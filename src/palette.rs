@@ -0,0 +1,125 @@
+// ANSI 256-color palette ------------------------------------------------------------------------ /
+
+/// Levels used by each channel of the 6x6x6 color cube (codes 16 to 231)
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The 16 system colors, as shown by xterm
+const SYSTEM_COLORS: [(u8, u8, u8); 16] = [
+    (0, 0, 0), (205, 0, 0), (0, 205, 0), (205, 205, 0),
+    (0, 0, 238), (205, 0, 205), (0, 205, 205), (229, 229, 229),
+    (127, 127, 127), (255, 0, 0), (0, 255, 0), (255, 255, 0),
+    (92, 92, 255), (255, 0, 255), (0, 255, 255), (255, 255, 255),
+];
+
+/// Looks up the (approximate) RGB value of an ANSI 256-color code, using the xterm palette.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::ansi256_to_rgb;
+///
+/// assert_eq!(ansi256_to_rgb(183), (0xd7, 0xaf, 0xff));
+/// ```
+pub fn ansi256_to_rgb(code: u8) -> (u8, u8, u8) {
+    match code {
+        0..=15 => SYSTEM_COLORS[code as usize],
+        16..=231 => {
+            let index = code - 16;
+            (
+                CUBE_LEVELS[(index / 36) as usize],
+                CUBE_LEVELS[(index / 6 % 6) as usize],
+                CUBE_LEVELS[(index % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (code - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+/// Finds the ANSI 256-color code closest to an RGB color, from the 6x6x6 color cube and the
+/// grayscale ramp (the 16 system colors are left out, since terminals often change them).
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::rgb_to_ansi256;
+///
+/// assert_eq!(rgb_to_ansi256(0xd7, 0xaf, 0xff), 183);
+/// assert_eq!(rgb_to_ansi256(128, 128, 130), 244);
+/// ```
+pub fn rgb_to_ansi256(red: u8, green: u8, blue: u8) -> u8 {
+    let cube_index = |channel: u8| {
+        (0..CUBE_LEVELS.len())
+            .min_by_key(|&index| (CUBE_LEVELS[index] as i32 - channel as i32).abs())
+            .unwrap_or(0) as u8
+    };
+    let cube = 16 + 36 * cube_index(red) + 6 * cube_index(green) + cube_index(blue);
+    let average = (red as u32 + green as u32 + blue as u32) / 3;
+    let gray = 232 + (average.saturating_sub(3) / 10).min(23) as u8;
+    let target = (red, green, blue);
+    if distance(ansi256_to_rgb(gray), target) < distance(ansi256_to_rgb(cube), target) {
+        gray
+    } else {
+        cube
+    }
+}
+
+/// Finds the closest of the 16 system colors (codes `0` to `15`) to an ANSI 256-color code.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::ansi256_to_ansi16;
+///
+/// assert_eq!(ansi256_to_ansi16(9), 9);
+/// assert_eq!(ansi256_to_ansi16(196), 9);
+/// assert_eq!(ansi256_to_ansi16(236), 0);
+/// ```
+pub fn ansi256_to_ansi16(code: u8) -> u8 {
+    if code < 16 {
+        return code;
+    }
+    let (red, green, blue) = ansi256_to_rgb(code);
+    rgb_to_ansi16(red, green, blue)
+}
+
+/// Finds the closest of the 16 system colors (codes `0` to `15`) to an RGB color
+pub(crate) fn rgb_to_ansi16(red: u8, green: u8, blue: u8) -> u8 {
+    (0..16u8)
+        .min_by_key(|&index| distance(SYSTEM_COLORS[index as usize], (red, green, blue)))
+        .unwrap_or(0)
+}
+
+/// Squared distance between two RGB colors
+fn distance(first: (u8, u8, u8), second: (u8, u8, u8)) -> u32 {
+    let channel = |a: u8, b: u8| (a as i32 - b as i32).pow(2) as u32;
+    channel(first.0, second.0) + channel(first.1, second.1) + channel(first.2, second.2)
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ansi256_to_rgb() {
+        assert_eq!(ansi256_to_rgb(1), (205, 0, 0));
+        assert_eq!(ansi256_to_rgb(16), (0, 0, 0));
+        assert_eq!(ansi256_to_rgb(231), (255, 255, 255));
+        assert_eq!(ansi256_to_rgb(232), (8, 8, 8));
+        assert_eq!(ansi256_to_rgb(255), (238, 238, 238));
+    }
+
+    #[test]
+    fn test_downsample_colors() {
+        assert_eq!(rgb_to_ansi256(0, 0, 0), 16);
+        assert_eq!(rgb_to_ansi256(255, 0, 0), 196);
+        assert_eq!(rgb_to_ansi256(8, 8, 8), 232);
+        for code in 16..=255u8 {
+            let (red, green, blue) = ansi256_to_rgb(code);
+            assert_eq!(ansi256_to_rgb(rgb_to_ansi256(red, green, blue)), (red, green, blue));
+        }
+        assert_eq!(ansi256_to_ansi16(21), 4);
+        assert_eq!(ansi256_to_ansi16(255), 7);
+    }
+}
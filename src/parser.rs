@@ -0,0 +1,519 @@
+use crate::ansi::push_group;
+use crate::terminal_style::TerminalStyle;
+
+// Constants ------------------------------------------------------------------------------------- /
+
+const ESC: u8 = 0x1B;
+const BEL: u8 = 0x07;
+const DEL: u8 = 0x7F;
+const C1_LEAD: u8 = 0xC2;
+const C1_ST: u8 = 0x9C;
+
+/// Maximum number of CSI parameters kept. Further parameters are dropped.
+const MAX_PARAMS: usize = 32;
+/// Maximum number of intermediate (and private marker) bytes kept.
+const MAX_INTERMEDIATES: usize = 4;
+/// Maximum number of bytes kept for an OSC or other control string. Further bytes are dropped.
+const MAX_STRING_LENGTH: usize = 64 * 1024;
+
+// Events ---------------------------------------------------------------------------------------- /
+
+/// Something found by the `AnsiParser` in the byte stream.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AnsiEvent<'a> {
+    /// Printable text (always complete UTF-8 characters)
+    Text(&'a str),
+    /// A C0 or C1 control character, like newline, tab or bell
+    Control(u32),
+    /// An SGR sequence (`ESC[...m`), as the style change it applies
+    Style(TerminalStyle),
+    /// Any other CSI sequence. Private markers (like `?`) are included in the intermediates.
+    Csi {
+        params: &'a [u16],
+        intermediates: &'a [u8],
+        action: char,
+    },
+    /// An OSC string (without the introducer or terminator), like `0;title`
+    Osc(&'a [u8]),
+    /// A DCS, SOS, PM or APC string (without the introducer or terminator).
+    /// The introducer is given by its `ESC` form: `P`, `X`, `^` or `_`.
+    ControlString {
+        introducer: u8,
+        data: &'a [u8],
+    },
+    /// A simple escape sequence, like `ESC 7` or `ESC ( B`
+    Esc {
+        intermediates: &'a [u8],
+        action: u8,
+    },
+    /// Bytes that are not valid UTF-8 or that can't be part of the sequence they appeared in
+    Invalid(&'a [u8]),
+}
+
+// Parser ---------------------------------------------------------------------------------------- /
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum State {
+    Ground,
+    Escape,
+    EscapeIntermediate,
+    Csi,
+    CsiIgnore,
+    Osc,
+    ControlString(u8),
+}
+
+/// A resumable parser for terminal output that accepts bytes in arbitrary chunks.
+/// Escape sequences and UTF-8 characters split across chunks are held until they are complete.
+/// Text is passed on without copying, and no memory is allocated per byte.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{AnsiParser, AnsiEvent, TerminalStyle};
+///
+/// let mut parser = AnsiParser::new();
+/// let mut styles = Vec::new();
+/// let mut text = String::new();
+/// for chunk in [&b"\x1b[0;3"[..], &b"1mHi\x1b"[..], &b"[0m"[..]].iter() {
+///     parser.advance(chunk, |event| match event {
+///         AnsiEvent::Style(style) => styles.push(style),
+///         AnsiEvent::Text(chunk) => text.push_str(chunk),
+///         _ => (),
+///     });
+/// }
+/// assert_eq!(styles, vec![TerminalStyle::red(), TerminalStyle::reset()]);
+/// assert_eq!(text, "Hi");
+/// ```
+#[derive(Debug, Clone)]
+pub struct AnsiParser {
+    state: State,
+    /// Collected CSI parameters
+    params: [u16; MAX_PARAMS],
+    param_count: usize,
+    /// Bit `n` is set when parameter `n` is followed by a colon, so the next one is its sub-parameter
+    colons: u32,
+    /// The CSI parameter currently being read
+    param: u16,
+    has_param: bool,
+    /// Collected intermediate bytes
+    intermediates: [u8; MAX_INTERMEDIATES],
+    intermediate_count: usize,
+    /// Collected OSC or control string data
+    string: Vec<u8>,
+    /// Whether an `ESC` was seen inside a control string (possibly starting its terminator)
+    string_escape: bool,
+    /// An incomplete UTF-8 character from the end of the last chunk
+    utf8: [u8; 4],
+    utf8_length: usize,
+}
+
+impl Default for AnsiParser {
+
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AnsiParser {
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Creates a new parser in the ground state
+    pub fn new() -> Self {
+        AnsiParser {
+            state: State::Ground,
+            params: [0; MAX_PARAMS],
+            param_count: 0,
+            colons: 0,
+            param: 0,
+            has_param: false,
+            intermediates: [0; MAX_INTERMEDIATES],
+            intermediate_count: 0,
+            string: Vec::new(),
+            string_escape: false,
+            utf8: [0; 4],
+            utf8_length: 0,
+        }
+    }
+
+    // Public instance methods ------------------------------------------------------------------- /
+
+    /// Parses the next chunk of bytes, calling `handler` with each event found.
+    /// Incomplete sequences at the end of the chunk are held until the next call.
+    pub fn advance<F: FnMut(AnsiEvent<'_>)>(&mut self, bytes: &[u8], mut handler: F) {
+        let mut index = 0;
+        while index < bytes.len() {
+            if self.state == State::Ground {
+                index += self.advance_ground(&bytes[index..], &mut handler);
+            } else if self.advance_sequence(bytes[index], &mut handler) {
+                index += 1;
+            }
+        }
+    }
+
+    /// Ends the stream, reporting anything that is still incomplete as invalid.
+    pub fn finish<F: FnMut(AnsiEvent<'_>)>(&mut self, mut handler: F) {
+        if self.utf8_length > 0 {
+            handler(AnsiEvent::Invalid(&self.utf8[..self.utf8_length]));
+            self.utf8_length = 0;
+        }
+        if self.state != State::Ground {
+            handler(AnsiEvent::Invalid(&[ESC]));
+            self.state = State::Ground;
+        }
+    }
+
+    /// Whether the parser is in the middle of a sequence or character
+    pub fn is_pending(&self) -> bool {
+        self.state != State::Ground || self.utf8_length > 0
+    }
+
+    // Private instance methods ------------------------------------------------------------------ /
+
+    /// Handles bytes in the ground state and returns how many were consumed.
+    fn advance_ground<F: FnMut(AnsiEvent<'_>)>(&mut self, bytes: &[u8], handler: &mut F) -> usize {
+        if self.utf8_length > 0 {
+            return self.continue_utf8(bytes[0], handler);
+        }
+        let first = bytes[0];
+        if first == ESC {
+            self.enter_escape();
+            return 1;
+        }
+        if first < 0x20 || first == DEL {
+            handler(AnsiEvent::Control(first as u32));
+            return 1;
+        }
+        if first == C1_LEAD && matches!(bytes.get(1), Some(0x80..=0x9F)) {
+            self.execute_c1(bytes[1], handler);
+            return 2;
+        }
+        let run = bytes.iter()
+            .enumerate()
+            .position(|(index, &byte)| {
+                byte < 0x20 || byte == DEL
+                    || (byte == C1_LEAD && matches!(bytes.get(index + 1), Some(0x80..=0x9F)))
+            })
+            .unwrap_or(bytes.len());
+        match std::str::from_utf8(&bytes[..run]) {
+            Ok(text) => {
+                handler(AnsiEvent::Text(text));
+                run
+            }
+            Err(error) => {
+                let valid = error.valid_up_to();
+                if valid > 0 {
+                    // Safe to unwrap, since this part was just validated
+                    handler(AnsiEvent::Text(std::str::from_utf8(&bytes[..valid]).unwrap()));
+                }
+                match error.error_len() {
+                    Some(length) => {
+                        handler(AnsiEvent::Invalid(&bytes[valid..valid + length]));
+                        valid + length
+                    }
+                    None if run == bytes.len() => {
+                        // Incomplete character at the end of the chunk
+                        let rest = &bytes[valid..run];
+                        self.utf8[..rest.len()].copy_from_slice(rest);
+                        self.utf8_length = rest.len();
+                        run
+                    }
+                    None => {
+                        handler(AnsiEvent::Invalid(&bytes[valid..run]));
+                        run
+                    }
+                }
+            }
+        }
+    }
+
+    /// Adds a byte to an incomplete UTF-8 character and returns how many bytes were consumed.
+    fn continue_utf8<F: FnMut(AnsiEvent<'_>)>(&mut self, byte: u8, handler: &mut F) -> usize {
+        if byte & 0xC0 != 0x80 {
+            // Not a continuation byte, so the held character is broken
+            handler(AnsiEvent::Invalid(&self.utf8[..self.utf8_length]));
+            self.utf8_length = 0;
+            return 0;
+        }
+        self.utf8[self.utf8_length] = byte;
+        self.utf8_length += 1;
+        let held = self.utf8;
+        match std::str::from_utf8(&held[..self.utf8_length]) {
+            Ok(text) => {
+                self.utf8_length = 0;
+                let character = text.chars().next().unwrap_or_default();
+                if ('\u{80}'..='\u{9F}').contains(&character) {
+                    self.execute_c1(held[1], handler);
+                } else {
+                    handler(AnsiEvent::Text(text));
+                }
+            }
+            Err(error) if error.error_len().is_some() => {
+                handler(AnsiEvent::Invalid(&held[..self.utf8_length]));
+                self.utf8_length = 0;
+            }
+            Err(_) => (),
+        }
+        1
+    }
+
+    /// Handles a C1 control character (given by its code point).
+    fn execute_c1<F: FnMut(AnsiEvent<'_>)>(&mut self, code: u8, handler: &mut F) {
+        match code {
+            0x9B => self.enter_csi(),
+            0x9D => self.enter_string(State::Osc),
+            0x90 => self.enter_string(State::ControlString(b'P')),
+            0x98 => self.enter_string(State::ControlString(b'X')),
+            0x9E => self.enter_string(State::ControlString(b'^')),
+            0x9F => self.enter_string(State::ControlString(b'_')),
+            C1_ST => (),
+            _ => handler(AnsiEvent::Control(code as u32)),
+        }
+    }
+
+    /// Handles a single byte while inside an escape sequence.
+    /// Returns `false` if the byte broke off the sequence and still needs to be handled as text.
+    fn advance_sequence<F: FnMut(AnsiEvent<'_>)>(&mut self, byte: u8, handler: &mut F) -> bool {
+        match self.state {
+            State::Ground => return false,
+            State::Escape | State::EscapeIntermediate => match byte {
+                b'[' if self.state == State::Escape => self.enter_csi(),
+                b']' if self.state == State::Escape => self.enter_string(State::Osc),
+                b'P' | b'X' | b'^' | b'_' if self.state == State::Escape => {
+                    self.enter_string(State::ControlString(byte))
+                }
+                0x20..=0x2F => {
+                    self.collect_intermediate(byte);
+                    self.state = State::EscapeIntermediate;
+                }
+                0x30..=0x7E => {
+                    handler(AnsiEvent::Esc {
+                        intermediates: &self.intermediates[..self.intermediate_count],
+                        action: byte,
+                    });
+                    self.state = State::Ground;
+                }
+                ESC => self.enter_escape(),
+                DEL => (),
+                0x00..=0x1F => handler(AnsiEvent::Control(byte as u32)),
+                _ => return self.abort(handler),
+            },
+            State::Csi | State::CsiIgnore => match byte {
+                b'0'..=b'9' => {
+                    self.param = self.param.saturating_mul(10).saturating_add((byte - b'0') as u16);
+                    self.has_param = true;
+                }
+                b';' => self.push_param(),
+                b':' => {
+                    if self.param_count < MAX_PARAMS {
+                        self.colons |= 1 << self.param_count;
+                    }
+                    self.push_param();
+                }
+                0x20..=0x2F | 0x3C..=0x3F => self.collect_intermediate(byte),
+                0x40..=0x7E => {
+                    if self.state == State::Csi {
+                        self.dispatch_csi(byte, handler);
+                    }
+                    self.state = State::Ground;
+                }
+                ESC => self.enter_escape(),
+                DEL => (),
+                0x00..=0x1F => handler(AnsiEvent::Control(byte as u32)),
+                _ => return self.abort(handler),
+            },
+            State::Osc | State::ControlString(_) => return self.advance_string(byte, handler),
+        }
+        true
+    }
+
+    /// Handles a single byte inside an OSC or other control string.
+    fn advance_string<F: FnMut(AnsiEvent<'_>)>(&mut self, byte: u8, handler: &mut F) -> bool {
+        if self.string_escape {
+            self.string_escape = false;
+            self.dispatch_string(handler);
+            if byte == b'\\' {
+                return true;
+            }
+            // Any other escape sequence ends the string and starts anew
+            self.enter_escape();
+            return self.advance_sequence(byte, handler);
+        }
+        match byte {
+            BEL if self.state == State::Osc => self.dispatch_string(handler),
+            ESC => self.string_escape = true,
+            C1_ST if self.string.last() == Some(&C1_LEAD) => {
+                self.string.pop();
+                self.dispatch_string(handler);
+            }
+            _ => {
+                if self.string.len() < MAX_STRING_LENGTH {
+                    self.string.push(byte);
+                }
+            }
+        }
+        true
+    }
+
+    fn enter_escape(&mut self) {
+        self.state = State::Escape;
+        self.intermediate_count = 0;
+    }
+
+    fn enter_csi(&mut self) {
+        self.state = State::Csi;
+        self.param_count = 0;
+        self.colons = 0;
+        self.param = 0;
+        self.has_param = false;
+        self.intermediate_count = 0;
+    }
+
+    fn enter_string(&mut self, state: State) {
+        self.state = state;
+        self.string.clear();
+        self.string_escape = false;
+    }
+
+    fn collect_intermediate(&mut self, byte: u8) {
+        if self.intermediate_count < MAX_INTERMEDIATES {
+            self.intermediates[self.intermediate_count] = byte;
+            self.intermediate_count += 1;
+        } else if self.state == State::Csi {
+            self.state = State::CsiIgnore;
+        }
+    }
+
+    fn push_param(&mut self) {
+        if self.param_count < MAX_PARAMS {
+            self.params[self.param_count] = self.param;
+            self.param_count += 1;
+        } else {
+            self.state = State::CsiIgnore;
+        }
+        self.param = 0;
+        self.has_param = false;
+    }
+
+    /// Leaves a sequence broken off by an unexpected byte, reporting its `ESC` as invalid.
+    /// Always returns `false`, since the unexpected byte still needs to be handled.
+    fn abort<F: FnMut(AnsiEvent<'_>)>(&mut self, handler: &mut F) -> bool {
+        handler(AnsiEvent::Invalid(&[ESC]));
+        self.state = State::Ground;
+        false
+    }
+
+    fn dispatch_csi<F: FnMut(AnsiEvent<'_>)>(&mut self, action: u8, handler: &mut F) {
+        if self.has_param || self.param_count > 0 {
+            self.push_param();
+        }
+        if self.state != State::Csi {
+            return;
+        }
+        let params = &self.params[..self.param_count];
+        if action == b'm' && self.intermediate_count == 0 {
+            handler(AnsiEvent::Style(TerminalStyle::from(self.sgr_codes())));
+        } else {
+            handler(AnsiEvent::Csi {
+                params,
+                intermediates: &self.intermediates[..self.intermediate_count],
+                action: action as char,
+            });
+        }
+    }
+
+    /// Gets the SGR codes of the collected parameters, reading colon groups (like `38:2::255:0:0`)
+    /// as one parameter
+    fn sgr_codes(&self) -> Vec<u8> {
+        if self.param_count == 0 {
+            return vec![0];
+        }
+        let mut codes = Vec::with_capacity(self.param_count);
+        let mut group = Vec::new();
+        for (index, param) in self.params[..self.param_count].iter().enumerate() {
+            group.push((*param).min(u8::MAX as u16) as u8);
+            if self.colons & (1 << index) == 0 || index + 1 == self.param_count {
+                push_group(&mut codes, &group);
+                group.clear();
+            }
+        }
+        codes
+    }
+
+    fn dispatch_string<F: FnMut(AnsiEvent<'_>)>(&mut self, handler: &mut F) {
+        match self.state {
+            State::Osc => handler(AnsiEvent::Osc(&self.string)),
+            State::ControlString(introducer) => handler(AnsiEvent::ControlString {
+                introducer,
+                data: &self.string,
+            }),
+            _ => (),
+        }
+        self.state = State::Ground;
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses all chunks and returns a debug description of each event
+    fn parse(chunks: &[&[u8]]) -> Vec<String> {
+        let mut parser = AnsiParser::new();
+        let mut events = Vec::new();
+        for chunk in chunks {
+            parser.advance(chunk, |event| events.push(format!("{:?}", event)));
+        }
+        parser.finish(|event| events.push(format!("{:?}", event)));
+        events
+    }
+
+    #[test]
+    fn test_whole_input() {
+        let events = parse(&[b"a\x1b[1;93mb\x1b[2J\x1b]0;title\x07\x1b(B\n"]);
+        assert_eq!(events, vec![
+            format!("{:?}", AnsiEvent::Text("a")),
+            format!("{:?}", AnsiEvent::Style(TerminalStyle::bright_yellow())),
+            format!("{:?}", AnsiEvent::Text("b")),
+            format!("{:?}", AnsiEvent::Csi { params: &[2], intermediates: &[], action: 'J' }),
+            format!("{:?}", AnsiEvent::Osc(b"0;title")),
+            format!("{:?}", AnsiEvent::Esc { intermediates: b"(", action: b'B' }),
+            format!("{:?}", AnsiEvent::Control(0x0A)),
+        ]);
+    }
+
+    #[test]
+    fn test_split_input() {
+        let whole = parse(&[b"\x1b[?25l\xe6\x97\xa5\x1b]8;;x\x1b\\\x1bPdata\x1b\\"]);
+        let split = parse(&[b"\x1b", b"[?2", b"5l\xe6", b"\x97", b"\xa5\x1b]8;", b";x\x1b", b"\\\x1bPda", b"ta\x1b", b"\\"]);
+        assert_eq!(whole, split);
+        assert_eq!(whole[1], format!("{:?}", AnsiEvent::Text("日")));
+    }
+
+    #[test]
+    fn test_invalid_input() {
+        let events = parse(&[b"a\xffb\xe6\x97"]);
+        assert_eq!(events, vec![
+            format!("{:?}", AnsiEvent::Text("a")),
+            format!("{:?}", AnsiEvent::Invalid(b"\xff")),
+            format!("{:?}", AnsiEvent::Text("b")),
+            format!("{:?}", AnsiEvent::Invalid(b"\xe6\x97")),
+        ]);
+        assert_eq!(parse(&[b"\x1b[m"]), vec![format!("{:?}", AnsiEvent::Style(TerminalStyle::reset()))]);
+        assert_eq!(parse(&[b"\x1b[3\xc3\xa9"]), vec![
+            format!("{:?}", AnsiEvent::Invalid(b"\x1b")),
+            format!("{:?}", AnsiEvent::Text("é")),
+        ]);
+    }
+
+    #[test]
+    fn test_colon_groups() {
+        let style = |codes: Vec<u8>| format!("{:?}", AnsiEvent::Style(TerminalStyle::from(codes)));
+        assert_eq!(parse(&[b"\x1b[38:2::255:0:0m"]), vec![style(vec![38, 2, 255, 0, 0])]);
+        assert_eq!(parse(&[b"\x1b[1;48:5:", b"21;4:3m"]), vec![style(vec![1, 48, 5, 21, 4])]);
+        assert_eq!(parse(&[b"\x1b[4:0;58:2:1:2:3m"]), vec![style(vec![24, 58, 2, 1, 2, 3])]);
+        assert_eq!(parse(&[b"\x1b[38;2;0;255;0m"]), vec![style(vec![38, 2, 0, 255, 0])]);
+    }
+}
@@ -0,0 +1,425 @@
+use crate::ansi::{ansi_segments, sgr_codes, AnsiSegment};
+use crate::detect::{color_level, ColorLevel, Stream};
+use crate::enums::srg_effect::SGREffect;
+use crate::palette::{ansi256_to_ansi16, rgb_to_ansi16, rgb_to_ansi256};
+use crate::terminfo::Terminfo;
+use std::borrow::Cow;
+use std::cell::Cell;
+use std::fmt::{self, Write};
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::OnceLock;
+
+// Render Policy --------------------------------------------------------------------------------- /
+
+/// Whether styled text is written with escape codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ColorMode {
+    /// Always writes escape codes
+    Always,
+    /// Never writes escape codes (SGR escape codes are left out, other escape sequences are kept)
+    Never,
+    /// Writes escape codes when the stream supports them (see `color_level`), without effects the
+    /// terminal's terminfo entry has no capability for
+    Auto(Stream),
+}
+
+/// How the `Display` impls of styled text (`TerminalStyle`, `StyledTerminalText`, `StyledLine`
+/// and `Styled`) write escape codes: whether they are written at all and the most colors to use.
+/// Colors beyond the level are replaced by the closest ones of the level.
+///
+/// The policy in use is set for the current thread (see `override_render_policy`), or else for
+/// the whole process (see `set_render_policy`). By default, all escape codes are written as is.
+/// `output()`, `wrap()` and `command()` always give the full escape codes.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{override_render_policy, ColorLevel, RenderPolicy, Stylize};
+///
+/// let text = "ok".rgb(0, 255, 0);
+/// assert_eq!(text.to_string(), "\u{001B}[38;2;0;255;0mok\u{001B}[0m");
+/// {
+///     let _guard = override_render_policy(RenderPolicy::always().max_level(ColorLevel::Ansi16));
+///     assert_eq!(text.to_string(), "\u{001B}[92mok\u{001B}[0m");
+/// }
+/// let _guard = override_render_policy(RenderPolicy::never());
+/// assert_eq!(text.to_string(), "ok");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct RenderPolicy {
+    /// Whether escape codes are written
+    pub mode: ColorMode,
+    /// The most colors to use, when escape codes are written
+    pub max_level: ColorLevel,
+}
+
+impl RenderPolicy {
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Creates a policy with the given mode, allowing 24-bit colors
+    pub fn new(mode: ColorMode) -> Self {
+        RenderPolicy { mode, max_level: ColorLevel::TrueColor }
+    }
+
+    /// Creates a policy that always writes escape codes
+    pub fn always() -> Self {
+        RenderPolicy::new(ColorMode::Always)
+    }
+
+    /// Creates a policy that never writes escape codes
+    pub fn never() -> Self {
+        RenderPolicy::new(ColorMode::Never)
+    }
+
+    /// Creates a policy that writes the escape codes a stream supports
+    pub fn auto(stream: Stream) -> Self {
+        RenderPolicy::new(ColorMode::Auto(stream))
+    }
+
+    /// Limits the colors used to the given level
+    pub fn max_level(self, max_level: ColorLevel) -> Self {
+        RenderPolicy { max_level, ..self }
+    }
+
+    // Instance methods -------------------------------------------------------------------------- /
+
+    /// Gets the color level text is written with. With `ColorMode::Auto`, the stream's level is
+    /// detected the first time it's needed, then remembered.
+    pub fn color_level(&self) -> ColorLevel {
+        let level = match self.mode {
+            ColorMode::Always => ColorLevel::TrueColor,
+            ColorMode::Never => ColorLevel::None,
+            ColorMode::Auto(stream) => detected_level(stream),
+        };
+        level.min(self.max_level)
+    }
+
+    /// Gets the terminfo entry whose effects text is limited to: the detected terminal's with
+    /// `ColorMode::Auto`, so effects it has no capability for are left out, otherwise none.
+    pub(crate) fn terminfo(&self) -> Option<&'static Terminfo> {
+        match self.mode {
+            ColorMode::Auto(_) => detected_terminfo(),
+            _ => None,
+        }
+    }
+
+    // Private helpers --------------------------------------------------------------------------- /
+
+    fn encode(&self) -> u8 {
+        let mode = match self.mode {
+            ColorMode::Always => 0,
+            ColorMode::Never => 1,
+            ColorMode::Auto(Stream::Stdout) => 2,
+            ColorMode::Auto(Stream::Stderr) => 3,
+        };
+        mode | (self.max_level as u8) << 2
+    }
+
+    fn decode(encoded: u8) -> Self {
+        let mode = match encoded & 0b11 {
+            0 => ColorMode::Always,
+            1 => ColorMode::Never,
+            2 => ColorMode::Auto(Stream::Stdout),
+            _ => ColorMode::Auto(Stream::Stderr),
+        };
+        let max_level = match encoded >> 2 {
+            0 => ColorLevel::None,
+            1 => ColorLevel::Ansi16,
+            2 => ColorLevel::Ansi256,
+            _ => ColorLevel::TrueColor,
+        };
+        RenderPolicy { mode, max_level }
+    }
+}
+
+impl Default for RenderPolicy {
+
+    /// Always writes escape codes, with 24-bit colors
+    fn default() -> Self {
+        RenderPolicy::always()
+    }
+}
+
+// Setting the policy ---------------------------------------------------------------------------- /
+
+/// The policy of the process, encoded (`Always` with 24-bit colors to start)
+static GLOBAL_POLICY: AtomicU8 = AtomicU8::new(0b1100);
+
+thread_local! {
+    static THREAD_POLICY: Cell<Option<RenderPolicy>> = const { Cell::new(None) };
+}
+
+/// Sets the render policy of the process, used by threads that don't set their own
+pub fn set_render_policy(policy: RenderPolicy) {
+    GLOBAL_POLICY.store(policy.encode(), Ordering::Relaxed);
+}
+
+/// Sets (or with `None`, clears) the render policy of the current thread, which takes precedence
+/// over the policy of the process. Returns the thread's previous policy.
+pub fn set_thread_render_policy(policy: Option<RenderPolicy>) -> Option<RenderPolicy> {
+    THREAD_POLICY.with(|current| current.replace(policy))
+}
+
+/// Gets the render policy in use on the current thread
+pub fn render_policy() -> RenderPolicy {
+    THREAD_POLICY.with(Cell::get)
+        .unwrap_or_else(|| RenderPolicy::decode(GLOBAL_POLICY.load(Ordering::Relaxed)))
+}
+
+/// Sets the render policy of the current thread until the returned guard is dropped, when the
+/// thread's previous policy is restored.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{override_render_policy, render_policy, RenderPolicy};
+///
+/// {
+///     let _guard = override_render_policy(RenderPolicy::never());
+///     assert_eq!(render_policy(), RenderPolicy::never());
+/// }
+/// assert_eq!(render_policy(), RenderPolicy::always());
+/// ```
+pub fn override_render_policy(policy: RenderPolicy) -> RenderPolicyGuard {
+    RenderPolicyGuard { previous: set_thread_render_policy(Some(policy)), _thread: PhantomData }
+}
+
+/// Restores the previous render policy of the thread when dropped (see `override_render_policy`)
+#[must_use = "the policy is restored as soon as the guard is dropped"]
+#[derive(Debug)]
+pub struct RenderPolicyGuard {
+    previous: Option<RenderPolicy>,
+    // The guard restores a thread's policy, so it must stay on that thread
+    _thread: PhantomData<*const ()>,
+}
+
+impl Drop for RenderPolicyGuard {
+
+    fn drop(&mut self) {
+        set_thread_render_policy(self.previous);
+    }
+}
+
+// Rendering ------------------------------------------------------------------------------------- /
+
+/// Command that resets the terminal to no styling
+pub(crate) const RESET: &str = "\u{001B}[0m";
+
+/// Rewrites the SGR escape codes of text for a color level: colors the level doesn't have are
+/// replaced by the closest ones it has, and with `ColorLevel::None` SGR escape codes are removed.
+/// Other escape sequences are kept.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{render_ansi, ColorLevel};
+///
+/// let text = "\u{001B}[1;38;2;255;0;0mred\u{001B}[0m";
+/// assert_eq!(render_ansi(text, ColorLevel::Ansi256), "\u{001B}[1;38;5;196mred\u{001B}[0m");
+/// assert_eq!(render_ansi(text, ColorLevel::Ansi16), "\u{001B}[1;91mred\u{001B}[0m");
+/// assert_eq!(render_ansi(text, ColorLevel::None), "red");
+/// ```
+pub fn render_ansi(text: &str, level: ColorLevel) -> Cow<'_, str> {
+    rewrite_sgr(text, level, None)
+}
+
+/// Rewrites the SGR escape codes of text for a render policy (see `render_ansi`)
+pub(crate) fn render_for_policy(text: &str, policy: RenderPolicy) -> Cow<'_, str> {
+    rewrite_sgr(text, policy.color_level(), policy.terminfo())
+}
+
+/// Rewrites the SGR escape codes of text for a color level, leaving out effects the terminfo
+/// entry (if any) has no capability for
+fn rewrite_sgr<'t>(text: &'t str, level: ColorLevel, terminfo: Option<&Terminfo>) -> Cow<'t, str> {
+    if writes_as_is(level, terminfo) {
+        return Cow::Borrowed(text);
+    }
+    let mut rendered = String::with_capacity(text.len());
+    for segment in ansi_segments(text) {
+        match segment {
+            AnsiSegment::Text(text) => rendered.push_str(text),
+            AnsiSegment::Escape(escape) => match sgr_codes(escape) {
+                Some(codes) => {
+                    let downsampled = downsample_codes(&codes, level, terminfo);
+                    if downsampled == codes {
+                        rendered.push_str(escape);
+                    } else if !downsampled.is_empty() {
+                        // Writing to a `String` can't fail
+                        let _ = write_sgr(&mut rendered, &downsampled);
+                    }
+                }
+                None => rendered.push_str(escape),
+            },
+        }
+    }
+    Cow::Owned(rendered)
+}
+
+/// Replaces the colors of SGR codes that a color level doesn't have with the closest ones it has,
+/// and leaves out effects the terminfo entry (if any) has no capability for.
+/// Gives no codes for `ColorLevel::None`.
+pub(crate) fn downsample_codes(codes: &[u8], level: ColorLevel, terminfo: Option<&Terminfo>) -> Vec<u8> {
+    if level == ColorLevel::None {
+        return Vec::new();
+    }
+    if writes_as_is(level, terminfo) {
+        return codes.to_vec();
+    }
+    let mut downsampled = Vec::with_capacity(codes.len());
+    let mut index = 0;
+    while index < codes.len() {
+        let code = codes[index];
+        match (code, &codes[index + 1..]) {
+            (38 | 48, [5, color, ..]) => {
+                if level >= ColorLevel::Ansi256 {
+                    downsampled.extend_from_slice(&[code, 5, *color]);
+                } else {
+                    downsampled.push(basic_code(code, ansi256_to_ansi16(*color)));
+                }
+                index += 3;
+            }
+            (38 | 48, [2, red, green, blue, ..]) => {
+                if level == ColorLevel::TrueColor {
+                    downsampled.extend_from_slice(&[code, 2, *red, *green, *blue]);
+                } else if level == ColorLevel::Ansi256 {
+                    downsampled.extend_from_slice(&[code, 5, rgb_to_ansi256(*red, *green, *blue)]);
+                } else {
+                    downsampled.push(basic_code(code, rgb_to_ansi16(*red, *green, *blue)));
+                }
+                index += 5;
+            }
+            // Underline colors are kept, so their parameters aren't taken for effects
+            (58, [5, _, ..]) | (58, [2, _, _, _, ..]) => {
+                let length = if codes[index + 1] == 5 { 3 } else { 5 };
+                downsampled.extend_from_slice(&codes[index..index + length]);
+                index += length;
+            }
+            (1..=9, _) if terminfo.is_some_and(|terminfo| !terminfo.supports_effect(&SGREffect::from(&code))) => {
+                index += 1;
+            }
+            _ => {
+                downsampled.push(code);
+                index += 1;
+            }
+        }
+    }
+    downsampled
+}
+
+/// Whether escape codes are written as is for a color level and terminfo entry (with all colors,
+/// and no effects to leave out)
+pub(crate) fn writes_as_is(level: ColorLevel, terminfo: Option<&Terminfo>) -> bool {
+    level == ColorLevel::TrueColor && terminfo.is_none()
+}
+
+/// Writes the escape codes of a style for a color level and terminfo entry (see `downsample_codes`).
+/// The style's `command`, if it has one, is written when the codes are written as is.
+pub(crate) fn write_style<W: Write>(out: &mut W, codes: &[u8], command: Option<&str>, level: ColorLevel, terminfo: Option<&Terminfo>) -> fmt::Result {
+    match (level, command) {
+        (ColorLevel::None, _) => Ok(()),
+        (_, Some(command)) if writes_as_is(level, terminfo) => out.write_str(command),
+        _ if writes_as_is(level, terminfo) => write_sgr(out, codes),
+        _ => write_sgr(out, &downsample_codes(codes, level, terminfo)),
+    }
+}
+
+/// Writes SGR codes as one escape sequence (nothing if there are no codes, as an empty sequence
+/// would reset the terminal)
+pub(crate) fn write_sgr<W: Write>(out: &mut W, codes: &[u8]) -> fmt::Result {
+    if codes.is_empty() {
+        return Ok(());
+    }
+    out.write_str("\u{001B}[")?;
+    for (index, code) in codes.iter().enumerate() {
+        if index > 0 {
+            out.write_str(";")?;
+        }
+        write!(out, "{}", code)?;
+    }
+    out.write_str("m")
+}
+
+/// Detects the color level of a stream once, then remembers it
+pub(crate) fn detected_level(stream: Stream) -> ColorLevel {
+    static STDOUT: OnceLock<ColorLevel> = OnceLock::new();
+    static STDERR: OnceLock<ColorLevel> = OnceLock::new();
+    let cell = match stream {
+        Stream::Stdout => &STDOUT,
+        Stream::Stderr => &STDERR,
+    };
+    *cell.get_or_init(|| color_level(stream))
+}
+
+/// Loads the terminfo entry of the terminal once, then remembers it
+pub(crate) fn detected_terminfo() -> Option<&'static Terminfo> {
+    static TERMINFO: OnceLock<Option<Terminfo>> = OnceLock::new();
+    TERMINFO.get_or_init(|| Terminfo::from_env().ok()).as_ref()
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Gets the code of one of the 16 system colors (`0` to `15`), as a foreground color for `38` or
+/// a background color for `48`
+fn basic_code(layer: u8, color: u8) -> u8 {
+    let base = if layer == 38 { 30 } else { 40 };
+    if color < 8 { base + color } else { base + 60 + color - 8 }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{StyledLine, StyledTerminalText, TerminalStyle};
+
+    #[test]
+    fn test_downsample_codes() {
+        let codes = [1, 38, 2, 255, 0, 0, 48, 5, 21, 4];
+        assert_eq!(downsample_codes(&codes, ColorLevel::TrueColor, None), codes.to_vec());
+        assert_eq!(downsample_codes(&codes, ColorLevel::Ansi256, None), vec![1, 38, 5, 196, 48, 5, 21, 4]);
+        assert_eq!(downsample_codes(&codes, ColorLevel::Ansi16, None), vec![1, 91, 44, 4]);
+        assert_eq!(downsample_codes(&codes, ColorLevel::None, None), vec![]);
+        assert_eq!(downsample_codes(&[38, 5], ColorLevel::Ansi16, None), vec![38, 5]);
+        // An entry without effect capabilities keeps colors (and their parameters) and resets
+        let plain = Terminfo::default();
+        let codes = [0, 1, 3, 38, 5, 3, 58, 5, 4, 48, 2, 1, 2, 3, 22];
+        assert_eq!(downsample_codes(&codes, ColorLevel::TrueColor, Some(&plain)), vec![0, 38, 5, 3, 58, 5, 4, 48, 2, 1, 2, 3, 22]);
+        assert_eq!(rewrite_sgr("\u{001B}[3ma\u{001B}[0m", ColorLevel::TrueColor, Some(&plain)), "a\u{001B}[0m");
+    }
+
+    #[test]
+    fn test_policy_encoding() {
+        for mode in [ColorMode::Always, ColorMode::Never, ColorMode::Auto(Stream::Stdout), ColorMode::Auto(Stream::Stderr)] {
+            for level in [ColorLevel::None, ColorLevel::Ansi16, ColorLevel::Ansi256, ColorLevel::TrueColor] {
+                let policy = RenderPolicy::new(mode).max_level(level);
+                assert_eq!(RenderPolicy::decode(policy.encode()), policy);
+            }
+        }
+        assert_eq!(RenderPolicy::decode(0b1100), RenderPolicy::default());
+    }
+
+    #[test]
+    fn test_display_follows_policy() {
+        let style = TerminalStyle::from(vec![0, 38, 5, 196]);
+        let text = StyledTerminalText::new("hot", style.clone());
+        let mut line = StyledLine::from(text.clone());
+        line.push_str("!");
+        {
+            let _outer = override_render_policy(RenderPolicy::always().max_level(ColorLevel::Ansi16));
+            assert_eq!(style.to_string(), "\u{001B}[0;91m");
+            assert_eq!(text.to_string(), "\u{001B}[0;91mhot\u{001B}[0m");
+            assert_eq!(format!("{:>5}", line), " \u{001B}[0;91mhot\u{001B}[0m!");
+            {
+                let _inner = override_render_policy(RenderPolicy::never());
+                assert_eq!(style.to_string(), "");
+                assert_eq!(text.to_string(), "hot");
+                assert_eq!(line.to_string(), "hot!");
+            }
+            assert_eq!(render_policy().max_level, ColorLevel::Ansi16);
+            // Raw output isn't changed by the policy
+            assert_eq!(line.output(), "\u{001B}[0;38;5;196mhot\u{001B}[0m!");
+            assert_eq!(text.output(), "\u{001B}[0;38;5;196mhot\u{001B}[0m");
+        }
+        assert_eq!(set_thread_render_policy(None), None);
+        assert_eq!(text.to_string(), text.output());
+    }
+}
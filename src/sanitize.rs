@@ -0,0 +1,94 @@
+use crate::ansi::{ansi_segments, sgr_codes, AnsiSegment};
+use std::borrow::Cow;
+
+// Sanitize Policy ------------------------------------------------------------------------------- /
+
+/// Describes how to handle control characters and escape sequences in untrusted text.
+/// In all cases, newlines and tabs are kept as they are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SanitizePolicy {
+    /// Makes control characters visible (`ESC` becomes `␛`), so nothing gets interpreted by the terminal
+    Escape,
+    /// Removes escape sequences and control characters
+    Strip,
+    /// Keeps SGR (style) sequences, but removes all other escape sequences and control characters
+    AllowStyles,
+}
+
+// Sanitize -------------------------------------------------------------------------------------- /
+
+/// Makes untrusted text safe to print, by preventing escape sequences (like clipboard writes,
+/// title changes or cursor moves) from reaching the terminal.
+/// Returns the original text (without copying) if there is nothing to sanitize.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{sanitize, SanitizePolicy};
+///
+/// let malicious = "file\u{001B}]52;c;ZWNobyBoaQ==\u{0007}.txt";
+/// assert_eq!(sanitize(malicious, SanitizePolicy::Escape), "file␛]52;c;ZWNobyBoaQ==␇.txt");
+/// assert_eq!(sanitize(malicious, SanitizePolicy::Strip), "file.txt");
+/// assert_eq!(sanitize("\u{001B}[1mbold\u{001B}[0m\u{001B}[2J", SanitizePolicy::AllowStyles), "\u{001B}[1mbold\u{001B}[0m");
+/// ```
+pub fn sanitize(text: &str, policy: SanitizePolicy) -> Cow<'_, str> {
+    if !text.chars().any(is_unsafe_control) {
+        return Cow::Borrowed(text);
+    }
+    let mut sanitized = String::with_capacity(text.len());
+    match policy {
+        SanitizePolicy::Escape => {
+            for character in text.chars() {
+                push_visible(&mut sanitized, character);
+            }
+        }
+        SanitizePolicy::Strip | SanitizePolicy::AllowStyles => {
+            for segment in ansi_segments(text) {
+                match segment {
+                    AnsiSegment::Escape(escape) if policy == SanitizePolicy::AllowStyles && sgr_codes(escape).is_some() => {
+                        sanitized.push_str(escape);
+                    }
+                    AnsiSegment::Escape(_) => (),
+                    AnsiSegment::Text(text) => {
+                        sanitized.extend(text.chars().filter(|character| !is_unsafe_control(*character)));
+                    }
+                }
+            }
+        }
+    }
+    Cow::Owned(sanitized)
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Whether the character is a control character other than newline or tab
+fn is_unsafe_control(character: char) -> bool {
+    character != '\n' && character != '\t' && character.is_control()
+}
+
+/// Pushes the character, replacing unsafe control characters with a visible representation.
+fn push_visible(output: &mut String, character: char) {
+    match character {
+        _ if !is_unsafe_control(character) => output.push(character),
+        // Control pictures block (`␀` to `␟`)
+        '\u{00}'..='\u{1F}' => output.push(std::char::from_u32(0x2400 + character as u32).unwrap_or('\u{FFFD}')),
+        '\u{7F}' => output.push('\u{2421}'),
+        // C1 controls don't have pictures
+        _ => output.push_str(&format!("\\u{{{:x}}}", character as u32)),
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert!(matches!(sanitize("line 1\n\tline 2", SanitizePolicy::Strip), Cow::Borrowed(_)));
+        assert_eq!(sanitize("a\rb\u{7F}\u{9B}2J", SanitizePolicy::Escape), "a␍b␡\\u{9b}2J");
+        assert_eq!(sanitize("a\u{001B}[2Jb\u{0008}c", SanitizePolicy::Strip), "abc");
+        assert_eq!(sanitize("a\u{001B}[?25lb\u{001B}[31mc", SanitizePolicy::AllowStyles), "ab\u{001B}[31mc");
+        assert_eq!(sanitize("\u{001B}]0;title\u{0007}", SanitizePolicy::AllowStyles), "");
+    }
+}
@@ -0,0 +1,196 @@
+use crate::sgr_state::SGRState;
+use crate::styled_line::{PlainLine, StyledLine};
+use crate::styled_terminal_text::StyledTerminalText;
+use crate::terminal_style::TerminalStyle;
+use std::fmt::{self, Debug, Formatter};
+use std::ops::Range;
+
+// Matcher --------------------------------------------------------------------------------------- /
+
+/// Finds the parts of text to highlight with `highlight_matches`.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::Matcher;
+///
+/// let digits = Matcher::custom(|text| {
+///     text.char_indices()
+///         .filter(|(_, character)| character.is_ascii_digit())
+///         .map(|(index, _)| index..index + 1)
+///         .collect()
+/// });
+/// assert_eq!(digits.find("a1b22"), vec![1..2, 3..4, 4..5]);
+/// assert_eq!(Matcher::CaseInsensitive("ab").find("xAbaB"), vec![1..3, 3..5]);
+/// ```
+pub enum Matcher<'a> {
+    /// Matches every occurrence of the text, including overlapping ones
+    Substring(&'a str),
+    /// Matches every occurrence of the text, ignoring case
+    CaseInsensitive(&'a str),
+    /// Gets the byte ranges to match from a closure, given the plain text
+    Custom(FindMatches<'a>),
+}
+
+/// A closure that gets the byte ranges to match in the given text
+pub type FindMatches<'a> = Box<dyn Fn(&str) -> Vec<Range<usize>> + 'a>;
+
+impl<'a> Matcher<'a> {
+
+    /// Creates a matcher that gets the byte ranges to match from a closure
+    pub fn custom<F: Fn(&str) -> Vec<Range<usize>> + 'a>(find: F) -> Self {
+        Matcher::Custom(Box::new(find))
+    }
+
+    /// Gets the byte ranges of all matches in the text, in the order they start.
+    /// Ranges that are empty, out of bounds or not on character boundaries are left out.
+    pub fn find(&self, text: &str) -> Vec<Range<usize>> {
+        let mut ranges = match self {
+            Matcher::Substring("") => Vec::new(),
+            Matcher::Substring(needle) => find_all(text, |start| text[start..].starts_with(needle).then_some(start + needle.len())),
+            Matcher::CaseInsensitive(needle) => find_all(text, |start| match_ignoring_case(&text[start..], needle).map(|length| start + length)),
+            Matcher::Custom(find) => find(text),
+        };
+        ranges.retain(|range| {
+            range.start < range.end && text.get(range.clone()).is_some()
+        });
+        ranges.sort_by_key(|range| (range.start, range.end));
+        ranges
+    }
+}
+
+impl<'a> Debug for Matcher<'a> {
+
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Matcher::Substring(needle) => formatter.debug_tuple("Substring").field(needle).finish(),
+            Matcher::CaseInsensitive(needle) => formatter.debug_tuple("CaseInsensitive").field(needle).finish(),
+            Matcher::Custom(_) => formatter.write_str("Custom(..)"),
+        }
+    }
+}
+
+// Highlight matches ----------------------------------------------------------------------------- /
+
+/// Highlights every match in styled text (like `grep --color`), overlaying the style on the styles
+/// the text already has: a red highlight on bold text gives bold red text. Overlapping and adjacent
+/// matches are highlighted as one.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{highlight_matches, Matcher, StyledLine, TerminalStyle};
+///
+/// let mut line = StyledLine::new();
+/// line.push_str("the cat sat on the ");
+/// line.push_styled("mat", TerminalStyle::from(vec![1]));
+/// let highlighted = highlight_matches(&line, &Matcher::Substring("at"), &TerminalStyle::red());
+/// assert_eq!(
+///     highlighted.output(),
+///     "the c\u{001B}[0;31mat\u{001B}[0m s\u{001B}[0;31mat\u{001B}[0m on the \u{001B}[1mm\u{001B}[0;1;31mat\u{001B}[0m"
+/// );
+/// ```
+pub fn highlight_matches(line: &StyledLine, matcher: &Matcher<'_>, style: &TerminalStyle) -> StyledLine {
+    let source = PlainLine::new(line);
+    // The style is overlaid, so it doesn't start with a reset
+    let codes: Vec<u8> = style.codes().iter().copied().skip_while(|code| *code == 0).collect();
+    let mut highlighted = StyledLine::new();
+    let mut position = 0;
+    for range in merge(matcher.find(&source.text)) {
+        source.push_range(&mut highlighted, position, range.start);
+        let mut matched = StyledLine::new();
+        source.push_range(&mut matched, range.start, range.end);
+        highlighted.extend(matched.spans().iter().map(|span| {
+            let mut state = SGRState::from_style(span.style());
+            state.apply(&codes);
+            StyledTerminalText::new(span.text(), state.to_style())
+        }));
+        position = range.end;
+    }
+    source.push_range(&mut highlighted, position, source.text.len());
+    highlighted
+}
+
+/// Highlights every match in text that may contain SGR escape codes. See `highlight_matches`.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{highlight_matches_ansi, Matcher, TerminalStyle};
+///
+/// let highlighted = highlight_matches_ansi("Error: error", &Matcher::CaseInsensitive("error"), &TerminalStyle::from(vec![7]));
+/// assert_eq!(highlighted, "\u{001B}[0;7mError\u{001B}[0m: \u{001B}[0;7merror\u{001B}[0m");
+/// ```
+pub fn highlight_matches_ansi(text: &str, matcher: &Matcher<'_>, style: &TerminalStyle) -> String {
+    highlight_matches(&StyledLine::parse(text), matcher, style).output()
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Finds the matches starting at each character boundary, given the end of the match at a start
+fn find_all(text: &str, match_at: impl Fn(usize) -> Option<usize>) -> Vec<Range<usize>> {
+    text.char_indices()
+        .filter_map(|(start, _)| match_at(start).map(|end| start..end))
+        .collect()
+}
+
+/// Gets the length of the start of the text that matches the needle, ignoring case
+fn match_ignoring_case(text: &str, needle: &str) -> Option<usize> {
+    let mut needle = needle.chars().flat_map(char::to_lowercase).peekable();
+    needle.peek()?;
+    let mut length = 0;
+    for character in text.chars() {
+        for lower in character.to_lowercase() {
+            if needle.next() != Some(lower) {
+                return None;
+            }
+        }
+        length += character.len_utf8();
+        if needle.peek().is_none() {
+            return Some(length);
+        }
+    }
+    None
+}
+
+/// Merges sorted ranges that overlap or touch
+fn merge(ranges: Vec<Range<usize>>) -> Vec<Range<usize>> {
+    let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+    for range in ranges {
+        match merged.last_mut() {
+            Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+            _ => merged.push(range),
+        }
+    }
+    merged
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_matches() {
+        assert_eq!(Matcher::Substring("aa").find("aaab"), vec![0..2, 1..3]);
+        assert_eq!(Matcher::Substring("").find("abc"), vec![]);
+        assert_eq!(Matcher::CaseInsensitive("STRASSE").find("die Straße"), vec![]);
+        assert_eq!(Matcher::CaseInsensitive("ǅ").find("ǆǄ"), vec![0..2, 2..4]);
+        let invalid = Matcher::custom(|_| vec![3..3, 0..1, 1..2, 0..9]);
+        assert_eq!(invalid.find("日本"), vec![]);
+    }
+
+    #[test]
+    fn test_highlight_matches() {
+        let highlight = TerminalStyle::from(vec![4]);
+        let highlighted = |text: &str, matcher: &Matcher| highlight_matches(&StyledLine::parse(text), matcher, &highlight);
+        let line = highlighted("aaab", &Matcher::Substring("aa"));
+        let texts: Vec<&str> = line.spans().iter().map(|span| span.text()).collect();
+        assert_eq!(texts, vec!["aaa", "b"]);
+        let adjacent = Matcher::custom(|_| vec![0..1, 1..2]);
+        assert_eq!(highlighted("abc", &adjacent).output(), "\u{001B}[0;4mab\u{001B}[0mc");
+        let styled = TerminalStyle::blue().wrap("abc");
+        assert_eq!(
+            highlighted(&styled, &Matcher::Substring("b")).output(),
+            "\u{001B}[0;34ma\u{001B}[0;4;34mb\u{001B}[0;34mc\u{001B}[0m"
+        );
+    }
+}
@@ -0,0 +1,350 @@
+use crate::terminal_style::TerminalStyle;
+use std::ops::RangeInclusive;
+
+// SGR Color ------------------------------------------------------------------------------------- /
+
+/// A foreground, background or underline color, as set by SGR codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SGRColor {
+    /// One of the 16 basic colors, stored by its code (like `31` or `41`)
+    Basic(u8),
+    /// An ANSI 256-color code
+    Indexed(u8),
+    /// A 24-bit color
+    Rgb(u8, u8, u8),
+}
+
+impl SGRColor {
+
+    /// Adds the codes selecting this color, using `extended` (`38`, `48` or `58`) for non-basic colors.
+    fn push_codes(&self, extended: u8, codes: &mut Vec<u8>) {
+        match *self {
+            SGRColor::Basic(code) => codes.push(code),
+            SGRColor::Indexed(index) => codes.extend_from_slice(&[extended, 5, index]),
+            SGRColor::Rgb(red, green, blue) => codes.extend_from_slice(&[extended, 2, red, green, blue]),
+        }
+    }
+}
+
+// SGR State ------------------------------------------------------------------------------------- /
+
+/// Tracks the graphic rendition state of a terminal, as changed by a series of SGR codes.
+/// This makes it possible to tell when two styles look the same and to find the shortest
+/// escape sequence that switches from one style to another.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{SGRState, TerminalStyle};
+///
+/// let mut state = SGRState::new();
+/// state.apply_style(&TerminalStyle::bright_yellow());
+/// assert_eq!(state.to_style(), TerminalStyle::from(vec![0, 1, 93]));
+///
+/// let mut target = state.clone();
+/// target.apply(&[22]);
+/// assert_eq!(state.transition_to(&target), Some(TerminalStyle::from(vec![22])));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct SGRState {
+    bold: bool,
+    faint: bool,
+    italic: bool,
+    /// Underline code (`4`, or `21` for double underline)
+    underline: Option<u8>,
+    /// Blink code (`5` or `6`)
+    blink: Option<u8>,
+    inverse: bool,
+    hidden: bool,
+    crossed_out: bool,
+    foreground: Option<SGRColor>,
+    background: Option<SGRColor>,
+    /// Underline color (never a basic color, since it's only set with `58`)
+    underline_color: Option<SGRColor>,
+    /// Any other codes that were applied (like fonts or overline), in order
+    others: Vec<u8>,
+}
+
+impl SGRState {
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Creates a new state with no styling (the terminal default)
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the state that results from applying the style to the terminal default
+    pub fn from_style(style: &TerminalStyle) -> Self {
+        let mut state = Self::new();
+        state.apply_style(style);
+        state
+    }
+
+    // Public instance methods ------------------------------------------------------------------- /
+
+    /// Whether the state is the terminal default (no styling)
+    pub fn is_default(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Applies the codes of a style
+    pub fn apply_style(&mut self, style: &TerminalStyle) {
+        self.apply(style.codes());
+    }
+
+    /// Applies a list of SGR codes, in order
+    pub fn apply(&mut self, codes: &[u8]) {
+        let mut index = 0;
+        while index < codes.len() {
+            let code = codes[index];
+            index += 1;
+            match code {
+                0 => *self = Self::default(),
+                1 => self.bold = true,
+                2 => self.faint = true,
+                3 => self.italic = true,
+                4 | 21 => self.underline = Some(code),
+                5 | 6 => self.blink = Some(code),
+                7 => self.inverse = true,
+                8 => self.hidden = true,
+                9 => self.crossed_out = true,
+                22 => {
+                    self.bold = false;
+                    self.faint = false;
+                }
+                23 => self.italic = false,
+                24 => self.underline = None,
+                25 => self.blink = None,
+                27 => self.inverse = false,
+                28 => self.hidden = false,
+                29 => self.crossed_out = false,
+                30..=37 | 90..=97 => self.foreground = Some(SGRColor::Basic(code)),
+                39 => self.foreground = None,
+                40..=47 | 100..=107 => self.background = Some(SGRColor::Basic(code)),
+                49 => self.background = None,
+                59 => self.underline_color = None,
+                38 | 48 | 58 => {
+                    let color = match codes.get(index..) {
+                        Some([5, color, ..]) => {
+                            index += 2;
+                            SGRColor::Indexed(*color)
+                        }
+                        Some([2, red, green, blue, ..]) => {
+                            index += 4;
+                            SGRColor::Rgb(*red, *green, *blue)
+                        }
+                        // A malformed color makes the rest of the codes meaningless
+                        _ => return,
+                    };
+                    match code {
+                        38 => self.foreground = Some(color),
+                        48 => self.background = Some(color),
+                        _ => self.underline_color = Some(color),
+                    }
+                }
+                _ => match other_group(code) {
+                    // Codes of a group replace each other, and its off code removes them
+                    Some((on, off)) => {
+                        self.others.retain(|other| !on.contains(other));
+                        if code != off {
+                            self.others.push(code);
+                        }
+                    }
+                    None => {
+                        if !self.others.contains(&code) {
+                            self.others.push(code);
+                        }
+                    }
+                },
+            }
+        }
+    }
+
+    /// Gets a state with only the background color of this state
+    pub(crate) fn background_only(&self) -> SGRState {
+        SGRState { background: self.background, ..Self::default() }
+    }
+
+    /// Gets a style that sets this state from any previous state (always starting with a reset)
+    pub fn to_style(&self) -> TerminalStyle {
+        TerminalStyle::from(self.full_codes())
+    }
+
+    /// Gets the shortest style that switches from this state to the target state,
+    /// or `None` if they are the same.
+    pub fn transition_to(&self, target: &SGRState) -> Option<TerminalStyle> {
+        if self == target {
+            return None;
+        }
+        let full = target.full_codes();
+        let codes = match self.incremental_codes(target) {
+            Some(incremental) if incremental.len() < full.len() => incremental,
+            _ => full,
+        };
+        Some(TerminalStyle::from(codes))
+    }
+
+    // Private instance methods ------------------------------------------------------------------ /
+
+    /// Codes that set this state after a reset
+    fn full_codes(&self) -> Vec<u8> {
+        Self::default().incremental_codes(self)
+            .map(|codes| {
+                let mut full = vec![0];
+                full.extend(codes);
+                full
+            })
+            .unwrap_or_else(|| vec![0])
+    }
+
+    /// Codes that switch from this state to the target without a reset,
+    /// or `None` if that isn't possible (because other codes can't be turned off).
+    fn incremental_codes(&self, target: &SGRState) -> Option<Vec<u8>> {
+        let mut codes = Vec::new();
+        for code in self.others.iter().filter(|code| !target.others.contains(code)) {
+            let (on, off) = other_group(*code)?;
+            // Another code of the group in the target replaces this one without the off code
+            if !target.others.iter().any(|other| on.contains(other)) && !codes.contains(&off) {
+                codes.push(off);
+            }
+        }
+        if (self.bold && !target.bold) || (self.faint && !target.faint) {
+            codes.push(22);
+            if target.bold {
+                codes.push(1);
+            }
+            if target.faint {
+                codes.push(2);
+            }
+        } else {
+            if target.bold && !self.bold {
+                codes.push(1);
+            }
+            if target.faint && !self.faint {
+                codes.push(2);
+            }
+        }
+        Self::push_flag(&mut codes, self.italic, target.italic, 3, 23);
+        if self.underline != target.underline {
+            codes.push(target.underline.unwrap_or(24));
+        }
+        if self.blink != target.blink {
+            codes.push(target.blink.unwrap_or(25));
+        }
+        Self::push_flag(&mut codes, self.inverse, target.inverse, 7, 27);
+        Self::push_flag(&mut codes, self.hidden, target.hidden, 8, 28);
+        Self::push_flag(&mut codes, self.crossed_out, target.crossed_out, 9, 29);
+        if self.foreground != target.foreground {
+            match target.foreground {
+                Some(color) => color.push_codes(38, &mut codes),
+                None => codes.push(39),
+            }
+        }
+        if self.background != target.background {
+            match target.background {
+                Some(color) => color.push_codes(48, &mut codes),
+                None => codes.push(49),
+            }
+        }
+        if self.underline_color != target.underline_color {
+            match target.underline_color {
+                Some(color) => color.push_codes(58, &mut codes),
+                None => codes.push(59),
+            }
+        }
+        codes.extend(target.others.iter().filter(|code| !self.others.contains(code)));
+        Some(codes)
+    }
+
+    fn push_flag(codes: &mut Vec<u8>, current: bool, target: bool, on: u8, off: u8) {
+        if current != target {
+            codes.push(if target { on } else { off });
+        }
+    }
+}
+
+/// Whether every code can be tracked by an `SGRState` (extended colors aren't cut short)
+pub(crate) fn is_well_formed(codes: &[u8]) -> bool {
+    let mut index = 0;
+    while index < codes.len() {
+        index += match (codes[index], &codes[index + 1..]) {
+            (38 | 48 | 58, [5, _, ..]) => 3,
+            (38 | 48 | 58, [2, _, _, _, ..]) => 5,
+            (38 | 48 | 58, _) => return false,
+            _ => 1,
+        };
+    }
+    true
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Gets the codes of the group an other code belongs to (which replace each other) and the code
+/// that turns them off, if it has one
+fn other_group(code: u8) -> Option<(RangeInclusive<u8>, u8)> {
+    let group = match code {
+        // Alternative fonts
+        10..=19 => (11..=19, 10),
+        // Proportional spacing
+        26 | 50 => (26..=26, 50),
+        // Framed and encircled
+        51 | 52 | 54 => (51..=52, 54),
+        // Overlined
+        53 | 55 => (53..=53, 55),
+        // Ideogram lines and stress marks
+        60..=65 => (60..=64, 65),
+        // Superscript and subscript
+        73..=75 => (73..=74, 75),
+        _ => return None,
+    };
+    Some(group)
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_codes() {
+        let mut state = SGRState::new();
+        state.apply(&[1, 3, 38, 5, 183, 48, 2, 1, 2, 3]);
+        assert_eq!(state.to_style().codes(), &vec![0, 1, 3, 38, 5, 183, 48, 2, 1, 2, 3]);
+        state.apply(&[22, 23, 39, 49]);
+        assert!(state.is_default());
+        assert_eq!(SGRState::from_style(&TerminalStyle::reset()), SGRState::new());
+        assert_eq!(SGRState::from_style(&TerminalStyle::red()), SGRState::from_style(&TerminalStyle::from(vec![31])));
+    }
+
+    #[test]
+    fn test_transitions() {
+        let red = SGRState::from_style(&TerminalStyle::red());
+        let bold_red = SGRState::from_style(&TerminalStyle::from(vec![1, 31]));
+        let faint_red = SGRState::from_style(&TerminalStyle::from(vec![2, 31]));
+        assert_eq!(red.transition_to(&red), None);
+        assert_eq!(SGRState::new().transition_to(&red), Some(TerminalStyle::from(vec![31])));
+        assert_eq!(red.transition_to(&bold_red), Some(TerminalStyle::from(vec![1])));
+        assert_eq!(bold_red.transition_to(&faint_red), Some(TerminalStyle::from(vec![22, 2])));
+        assert_eq!(bold_red.transition_to(&SGRState::new()), Some(TerminalStyle::reset()));
+        let fraktur = SGRState::from_style(&TerminalStyle::from(vec![20, 31]));
+        assert_eq!(fraktur.transition_to(&bold_red), Some(TerminalStyle::from(vec![0, 1, 31])));
+        let overlined = SGRState::from_style(&TerminalStyle::from(vec![53, 31]));
+        assert_eq!(overlined.transition_to(&red), Some(TerminalStyle::from(vec![55])));
+    }
+
+    #[test]
+    fn test_underline_colors_and_off_codes() {
+        let mut state = SGRState::new();
+        state.apply(&[4, 58, 2, 255, 0, 0]);
+        assert_eq!(state.to_style().codes(), &vec![0, 4, 58, 2, 255, 0, 0]);
+        state.apply(&[58, 5, 196]);
+        assert_eq!(state.to_style().codes(), &vec![0, 4, 58, 5, 196]);
+        state.apply(&[59, 24]);
+        assert!(state.is_default());
+        state.apply(&[53, 51, 52, 55, 54]);
+        assert!(state.is_default());
+        state.apply(&[73, 74]);
+        assert_eq!(state.to_style().codes(), &vec![0, 74]);
+    }
+}
@@ -0,0 +1,110 @@
+use crate::styled_line::{PlainLine, StyledLine};
+use crate::styled_terminal_text::StyledTerminalText;
+use crate::terminal_style::TerminalStyle;
+use crate::width::WidthOptions;
+use std::ops::Range;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Slice ----------------------------------------------------------------------------------------- /
+
+/// Gets the part of styled text shown in a range of terminal columns (like columns `40..120` of a
+/// line, for horizontal scrolling). Each piece of text keeps the style it had, so the slice is
+/// self-contained. Wide characters and tabs cut by either end of the range are replaced by spaces
+/// (in the same style) for the columns that fall inside it, so the slice is always exactly as wide
+/// as the part of the range covered by the text.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{slice_styled, StyledLine, TerminalStyle};
+///
+/// let mut line = StyledLine::new();
+/// line.push_str("log: ");
+/// line.push_styled("日本語", TerminalStyle::red());
+/// let sliced = slice_styled(&line, 3..8);
+/// assert_eq!(sliced.plain(), ": 日 ");
+/// assert_eq!(sliced.output(), ": \u{001B}[0;31m日 \u{001B}[0m");
+/// ```
+pub fn slice_styled(line: &StyledLine, columns: Range<usize>) -> StyledLine {
+    slice_styled_with(line, columns, &WidthOptions::default())
+}
+
+/// Gets the part of styled text shown in a range of terminal columns, using the given options to
+/// measure the text. See `slice_styled`.
+pub fn slice_styled_with(line: &StyledLine, columns: Range<usize>, options: &WidthOptions) -> StyledLine {
+    let source = PlainLine::new(line);
+    let mut sliced = StyledLine::new();
+    // Byte range of the graphemes currently being kept as they are
+    let mut kept: Option<(usize, usize)> = None;
+    let mut column = 0;
+    for (offset, grapheme) in source.text.grapheme_indices(true) {
+        if column >= columns.end {
+            break;
+        }
+        let next = options.advance(column, grapheme);
+        // Tabs are always expanded, since their width depends on the column they start at
+        if column >= columns.start && next <= columns.end && grapheme != "\t" {
+            let (start, _) = kept.unwrap_or((offset, offset));
+            kept = Some((start, offset + grapheme.len()));
+        } else {
+            if let Some((start, end)) = kept.take() {
+                source.push_range(&mut sliced, start, end);
+            }
+            let covered = next.min(columns.end).saturating_sub(column.max(columns.start));
+            if covered > 0 {
+                let style = source.style_at(offset).cloned().unwrap_or_else(TerminalStyle::no_color);
+                sliced.push(StyledTerminalText::new(&" ".repeat(covered), style));
+            }
+        }
+        column = next;
+    }
+    if let Some((start, end)) = kept {
+        source.push_range(&mut sliced, start, end);
+    }
+    sliced
+}
+
+/// Gets the part of text containing SGR escape codes shown in a range of terminal columns.
+/// See `slice_styled`.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{slice_ansi, highlight_red};
+///
+/// let text = format!("[{}]", highlight_red("error: file not found"));
+/// assert_eq!(slice_ansi(&text, 8..12), "\u{001B}[0;31mfile\u{001B}[0m");
+/// ```
+pub fn slice_ansi(text: &str, columns: Range<usize>) -> String {
+    slice_styled(&StyledLine::parse(text), columns).output()
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sliced(text: &str, columns: Range<usize>) -> String {
+        slice_styled(&StyledLine::parse(text), columns).plain()
+    }
+
+    #[test]
+    fn test_slice_columns() {
+        assert_eq!(sliced("abcdef", 0..3), "abc");
+        assert_eq!(sliced("abcdef", 4..10), "ef");
+        assert_eq!(sliced("abcdef", 8..10), "");
+        assert_eq!(sliced("abcdef", 3..3), "");
+        assert_eq!(sliced("日本語", 1..5), " 本 ");
+        assert_eq!(sliced("e\u{301}x", 0..1), "e\u{301}");
+        assert_eq!(sliced("a\tb", 0..9), "a       b");
+        assert_eq!(sliced("a\tb", 6..9), "  b");
+    }
+
+    #[test]
+    fn test_slice_styles() {
+        let text = format!("ab{}", TerminalStyle::blue().wrap("cd日"));
+        assert_eq!(slice_ansi(&text, 3..5), "\u{001B}[0;34md \u{001B}[0m");
+        assert_eq!(slice_ansi(&text, 1..3), "b\u{001B}[0;34mc\u{001B}[0m");
+        // Other escape sequences are dropped, since they may not apply to the slice
+        assert_eq!(slice_ansi("ab\u{001B}[2Kcd", 1..3), "bc");
+    }
+}
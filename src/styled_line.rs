@@ -0,0 +1,504 @@
+use crate::ansi::{ansi_segments, sgr_codes, strip_ansi, AnsiSegment};
+use crate::pad::{format_line, pad_styled, PadOptions};
+use crate::render::{render_for_policy, render_policy, writes_as_is};
+use crate::sgr_state::SGRState;
+use crate::styled_terminal_text::StyledTerminalText;
+use crate::terminal_style::TerminalStyle;
+use crate::width::{visible_width, visible_width_with, WidthOptions};
+use crate::search::{highlight_matches, Matcher};
+use crate::slice::slice_styled;
+use crate::truncate::{truncate_styled, TruncateOptions};
+use crate::wrap::{wrap_styled, WrapOptions};
+use std::fmt::{Alignment, Display, Error, Formatter, Write};
+use std::iter::FromIterator;
+use std::ops::{Add, AddAssign, Range};
+
+// Styled Line ----------------------------------------------------------------------------------- /
+
+/// Holds a line of text made of differently styled spans, in order.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{StyledLine, TerminalStyle};
+///
+/// let mut line = StyledLine::new();
+/// line.push_styled("error", TerminalStyle::bright_red());
+/// line.push_str(": file ");
+/// line.push_styled("foo.rs", TerminalStyle::italic_white());
+/// line.push_str(" not found");
+/// assert_eq!(line.plain(), "error: file foo.rs not found");
+/// assert_eq!(line.visible_width(), 28);
+/// assert_eq!(
+///     line.output(),
+///     "\u{001B}[1;91merror\u{001B}[0m: file \u{001B}[3;37mfoo.rs\u{001B}[0m not found"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct StyledLine {
+    spans: Vec<StyledTerminalText>,
+}
+
+impl StyledLine {
+
+    /// Gets the styled spans, in order
+    pub fn spans(&self) -> &[StyledTerminalText] {
+        &self.spans
+    }
+
+    /// Gets the text without any styling
+    pub fn plain(&self) -> String {
+        self.spans.iter().map(|span| span.plain()).collect()
+    }
+
+    /// Gets the number of terminal columns the line occupies (see `visible_width`)
+    pub fn visible_width(&self) -> usize {
+        visible_width(&self.plain())
+    }
+
+    /// Gets the number of terminal columns the line occupies, using the given options
+    pub fn visible_width_with(&self, options: &WidthOptions) -> usize {
+        visible_width_with(&self.plain(), options)
+    }
+
+    /// Whether the line has no visible text
+    pub fn is_empty(&self) -> bool {
+        self.spans.iter().all(|span| span.plain().is_empty())
+    }
+
+    /// Gets output for terminal. Adjacent spans with the same style share their escape codes.
+    pub fn output(&self) -> String {
+        let mut output = String::new();
+        // Writing to a `String` can't fail
+        let _ = self.write_spans(&mut output);
+        output
+    }
+
+    /// Adds a styled span to the end of the line
+    pub fn push(&mut self, span: StyledTerminalText) {
+        self.spans.push(span);
+    }
+
+    /// Adds un-styled text to the end of the line
+    pub fn push_str(&mut self, text: &str) {
+        self.push(StyledTerminalText::new(text, TerminalStyle::no_color()));
+    }
+
+    /// Adds text in the given style to the end of the line
+    pub fn push_styled(&mut self, text: &str, style: TerminalStyle) {
+        self.push(StyledTerminalText::new(text, style));
+    }
+
+    /// Wraps the line into lines no wider than the given width (see `wrap_styled`)
+    pub fn wrap(&self, options: &WrapOptions) -> Vec<StyledLine> {
+        wrap_styled(self, options)
+    }
+
+    /// Cuts the line to fit in the given width, adding an ellipsis (see `truncate_styled`)
+    pub fn truncate(&self, options: &TruncateOptions) -> StyledLine {
+        truncate_styled(self, options)
+    }
+
+    /// Gets the part of the line shown in a range of terminal columns (see `slice_styled`)
+    pub fn slice(&self, columns: Range<usize>) -> StyledLine {
+        slice_styled(self, columns)
+    }
+
+    /// Highlights every match in the line with the style (see `highlight_matches`)
+    pub fn highlight_matches(&self, matcher: &Matcher<'_>, style: &TerminalStyle) -> StyledLine {
+        highlight_matches(self, matcher, style)
+    }
+
+    /// Pads the line to the given width (see `pad_styled`)
+    pub fn pad(&self, options: &PadOptions) -> StyledLine {
+        pad_styled(self, options)
+    }
+
+    /// Adds padding to the left of the line, so it is right-aligned within the given width.
+    /// With `extend_background`, the padding takes the background color of the start of the line.
+    pub fn pad_left(&self, width: usize, extend_background: bool) -> StyledLine {
+        self.pad(&PadOptions { extend_background, ..PadOptions::new(width, Alignment::Right) })
+    }
+
+    /// Adds padding to the right of the line, so it is left-aligned within the given width.
+    /// With `extend_background`, the padding takes the background color of the end of the line.
+    pub fn pad_right(&self, width: usize, extend_background: bool) -> StyledLine {
+        self.pad(&PadOptions { extend_background, ..PadOptions::new(width, Alignment::Left) })
+    }
+
+    /// Adds padding to both sides of the line, so it is centered within the given width.
+    /// With `extend_background`, the padding takes the background color of the text next to it.
+    ///
+    /// # Examples
+    /// ```
+    /// use terminal_text_styler::{StyledLine, TerminalStyle};
+    ///
+    /// let title = StyledLine::parse(&TerminalStyle::from(vec![30, 47]).wrap("Title"));
+    /// assert_eq!(
+    ///     title.center(9, true).output(),
+    ///     "\u{001B}[0;47m  \u{001B}[0;30;47mTitle\u{001B}[0;47m  \u{001B}[0m"
+    /// );
+    /// assert_eq!(title.center(9, false).output(), "  \u{001B}[0;30;47mTitle\u{001B}[0m  ");
+    /// ```
+    pub fn center(&self, width: usize, extend_background: bool) -> StyledLine {
+        self.pad(&PadOptions { extend_background, ..PadOptions::new(width, Alignment::Center) })
+    }
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Creates an empty line
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a line from text that already contains SGR escape codes, with one span for each
+    /// change of style. Other escape sequences are kept in the text of the span they appear in.
+    ///
+    /// # Examples
+    /// ```
+    /// use terminal_text_styler::{StyledLine, TerminalStyle, highlight_red};
+    ///
+    /// let line = StyledLine::parse(&format!("a {} b", highlight_red("red")));
+    /// assert_eq!(line.spans().len(), 3);
+    /// assert_eq!(line.spans()[1].style(), &TerminalStyle::red());
+    /// ```
+    pub fn parse(text: &str) -> Self {
+        let mut line = Self::new();
+        let mut state = SGRState::new();
+        let mut current = String::new();
+        let mut current_state = SGRState::new();
+        for segment in ansi_segments(text) {
+            let (text, codes) = match segment {
+                AnsiSegment::Escape(escape) => match sgr_codes(escape) {
+                    Some(codes) => ("", Some(codes)),
+                    None => (escape, None),
+                },
+                AnsiSegment::Text(text) => (text, None),
+            };
+            if let Some(codes) = codes {
+                state.apply(&codes);
+                continue;
+            }
+            if state != current_state {
+                if !current.is_empty() {
+                    line.push(Self::span(&current, &current_state));
+                    current.clear();
+                }
+                current_state = state.clone();
+            }
+            current.push_str(text);
+        }
+        if !current.is_empty() {
+            line.push(Self::span(&current, &current_state));
+        }
+        line
+    }
+
+    /// Joins styled values into one line, with the separator between them. Each value keeps its spans.
+    ///
+    /// # Examples
+    /// ```
+    /// use terminal_text_styler::{StyledLine, highlight_red, highlight_blue};
+    ///
+    /// let line = StyledLine::join(vec![highlight_red("a"), highlight_blue("b")], ", ");
+    /// assert_eq!(line.plain(), "a, b");
+    /// assert_eq!(line.spans().len(), 3);
+    /// ```
+    pub fn join<I, S>(items: I, separator: S) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<StyledLine>,
+        S: Into<StyledLine>,
+    {
+        let separator = separator.into();
+        let mut line = Self::new();
+        for (index, item) in items.into_iter().enumerate() {
+            if index > 0 {
+                line.spans.extend(separator.spans.iter().cloned());
+            }
+            line += item.into();
+        }
+        line
+    }
+
+    // Private instance methods ------------------------------------------------------------------ /
+
+    fn span(text: &str, state: &SGRState) -> StyledTerminalText {
+        StyledTerminalText::new(text, state.to_style())
+    }
+
+    /// Writes the spans, sharing escape codes between adjacent spans with the same style
+    fn write_spans<W: Write>(&self, formatter: &mut W) -> Result<(), Error> {
+        let mut active: Option<&TerminalStyle> = None;
+        for span in self.spans.iter().filter(|span| !span.text().is_empty()) {
+            let style = span.style();
+            if SGRState::from_style(style).is_default() {
+                if active.take().is_some() {
+                    formatter.write_str(TerminalStyle::reset().command())?;
+                }
+            } else if active != Some(style) {
+                // Styles that don't start with a reset would add to the previous style
+                if active.is_some() && style.codes().first() != Some(&0) {
+                    formatter.write_str(TerminalStyle::reset().command())?;
+                }
+                formatter.write_str(style.command())?;
+                active = Some(style);
+            }
+            formatter.write_str(span.text())?;
+        }
+        if active.is_some() {
+            formatter.write_str(TerminalStyle::reset().command())?;
+        }
+        Ok(())
+    }
+}
+
+// Plain Line ------------------------------------------------------------------------------------ /
+
+/// The plain text of a line, with the style of each span's range of it.
+/// Used to cut lines at any position of the plain text and style the pieces again.
+pub(crate) struct PlainLine<'a> {
+    pub(crate) text: String,
+    ranges: Vec<(usize, usize, &'a TerminalStyle)>,
+}
+
+impl<'a> PlainLine<'a> {
+
+    pub(crate) fn new(line: &'a StyledLine) -> Self {
+        let mut text = String::new();
+        let mut ranges = Vec::with_capacity(line.spans.len());
+        for span in line.spans.iter() {
+            let start = text.len();
+            text.push_str(&span.plain());
+            ranges.push((start, text.len(), span.style()));
+        }
+        PlainLine { text, ranges }
+    }
+
+    /// Gets the style of the text at the given byte offset of the plain text
+    pub(crate) fn style_at(&self, offset: usize) -> Option<&'a TerminalStyle> {
+        self.ranges.iter()
+            .find(|(start, end, _)| *start <= offset && offset < *end)
+            .map(|(_, _, style)| *style)
+    }
+
+    /// Adds the given byte range of the plain text to the line, with the styles it originally had
+    pub(crate) fn push_range(&self, line: &mut StyledLine, start: usize, end: usize) {
+        for (span_start, span_end, style) in self.ranges.iter() {
+            let piece_start = start.max(*span_start);
+            let piece_end = end.min(*span_end);
+            if piece_start < piece_end {
+                line.push(StyledTerminalText::new(&self.text[piece_start..piece_end], (*style).clone()));
+            }
+        }
+    }
+}
+
+impl Display for StyledLine {
+
+    /// Writes the output for terminal. Width, fill, alignment and precision (which truncates)
+    /// are measured in terminal columns, so `{:>10}` lines up regardless of escape codes.
+    /// Escape codes follow the render policy (see `RenderPolicy`).
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), Error> {
+        let formatted = format_line(self, formatter);
+        let line = formatted.as_ref().unwrap_or(self);
+        let policy = render_policy();
+        if writes_as_is(policy.color_level(), policy.terminfo()) {
+            line.write_spans(formatter)
+        } else {
+            formatter.write_str(&render_for_policy(&line.output(), policy))
+        }
+    }
+}
+
+impl Extend<StyledTerminalText> for StyledLine {
+
+    fn extend<T: IntoIterator<Item = StyledTerminalText>>(&mut self, spans: T) {
+        self.spans.extend(spans);
+    }
+}
+
+impl Extend<StyledLine> for StyledLine {
+
+    fn extend<T: IntoIterator<Item = StyledLine>>(&mut self, lines: T) {
+        for line in lines {
+            self.spans.extend(line.spans);
+        }
+    }
+}
+
+impl FromIterator<StyledTerminalText> for StyledLine {
+
+    fn from_iter<T: IntoIterator<Item = StyledTerminalText>>(spans: T) -> Self {
+        let mut line = Self::new();
+        line.extend(spans);
+        line
+    }
+}
+
+impl FromIterator<StyledLine> for StyledLine {
+
+    fn from_iter<T: IntoIterator<Item = StyledLine>>(lines: T) -> Self {
+        let mut line = Self::new();
+        line.extend(lines);
+        line
+    }
+}
+
+// Concatenation --------------------------------------------------------------------------------- /
+
+impl AddAssign<StyledLine> for StyledLine {
+
+    fn add_assign(&mut self, other: StyledLine) {
+        self.spans.extend(other.spans);
+    }
+}
+
+impl AddAssign<StyledTerminalText> for StyledLine {
+
+    fn add_assign(&mut self, other: StyledTerminalText) {
+        *self += StyledLine::from(other);
+    }
+}
+
+impl AddAssign<&str> for StyledLine {
+
+    fn add_assign(&mut self, other: &str) {
+        *self += StyledLine::from(other);
+    }
+}
+
+impl<T> Add<T> for StyledLine where StyledLine: AddAssign<T> {
+    type Output = StyledLine;
+
+    /// Adds text to the end of the line, keeping the spans of both
+    ///
+    /// # Examples
+    /// ```
+    /// use terminal_text_styler::{highlight_red, highlight_bright_white};
+    ///
+    /// let message = highlight_red("error") + ": cannot open " + highlight_bright_white("foo.rs");
+    /// assert_eq!(message.plain(), "error: cannot open foo.rs");
+    /// assert_eq!(message.visible_width(), 25);
+    /// ```
+    fn add(mut self, other: T) -> StyledLine {
+        self += other;
+        self
+    }
+}
+
+impl<T> Add<T> for StyledTerminalText where StyledLine: AddAssign<T> {
+    type Output = StyledLine;
+
+    fn add(self, other: T) -> StyledLine {
+        StyledLine::from(self) + other
+    }
+}
+
+impl Add<StyledTerminalText> for &str {
+    type Output = StyledLine;
+
+    fn add(self, other: StyledTerminalText) -> StyledLine {
+        StyledLine::from(self) + other
+    }
+}
+
+impl Add<StyledLine> for &str {
+    type Output = StyledLine;
+
+    fn add(self, other: StyledLine) -> StyledLine {
+        StyledLine::from(self) + other
+    }
+}
+
+// Conversions ----------------------------------------------------------------------------------- /
+
+impl From<StyledTerminalText> for StyledLine {
+
+    /// Makes a line with a single span (or one span per style, if the text has embedded escape codes)
+    fn from(text: StyledTerminalText) -> Self {
+        if strip_ansi(text.text()).len() == text.text().len() {
+            StyledLine { spans: vec![text] }
+        } else {
+            Self::parse(text.output())
+        }
+    }
+}
+
+impl From<&str> for StyledLine {
+
+    /// Makes a line from text that may contain SGR escape codes (see `parse`)
+    fn from(text: &str) -> Self {
+        Self::parse(text)
+    }
+}
+
+impl From<StyledLine> for StyledTerminalText {
+
+    /// Makes a single styled text from the line. A line with exactly one span gives that span;
+    /// otherwise, the text holds the escape codes of all spans.
+    fn from(mut line: StyledLine) -> Self {
+        if line.spans.len() == 1 {
+            return line.spans.remove(0);
+        }
+        StyledTerminalText::new(&line.output(), TerminalStyle::no_color())
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output() {
+        let mut line = StyledLine::new();
+        line.push_styled("a", TerminalStyle::red());
+        line.push_styled("b", TerminalStyle::red());
+        line.push_styled("c", TerminalStyle::from(vec![1]));
+        line.push_str("d");
+        assert_eq!(line.output(), "\u{001B}[0;31mab\u{001B}[0m\u{001B}[1mc\u{001B}[0md");
+        assert_eq!(StyledLine::new().output(), "");
+        assert!(StyledLine::from("\u{001B}[1m\u{001B}[0m").is_empty());
+    }
+
+    #[test]
+    fn test_parse() {
+        let text = format!("x {} {}", TerminalStyle::red().wrap("a"), TerminalStyle::bright_yellow().wrap("\u{001B}[Kb"));
+        let line = StyledLine::parse(&text);
+        let texts: Vec<&str> = line.spans().iter().map(|span| span.text()).collect();
+        assert_eq!(texts, vec!["x ", "a", " ", "\u{001B}[Kb"]);
+        assert_eq!(line.spans()[3].style(), &TerminalStyle::from(vec![0, 1, 93]));
+        assert_eq!(line.plain(), "x a b");
+        assert_eq!(StyledLine::parse(&line.output()), line);
+    }
+
+    #[test]
+    fn test_concatenation() {
+        let red = StyledTerminalText::new("a", TerminalStyle::red());
+        let mut line = "> " + red.clone() + " " + StyledTerminalText::new("b", TerminalStyle::blue());
+        line += "!";
+        assert_eq!(line.spans().len(), 5);
+        assert_eq!(line.plain(), "> a b!");
+        line += StyledLine::join(vec!["x", "y"], red.clone());
+        assert_eq!(line.plain(), "> a b!xay");
+        let collected: StyledLine = vec![red.clone(), red].into_iter().collect();
+        assert_eq!(collected.output(), "\u{001B}[0;31maa\u{001B}[0m");
+        let lines: StyledLine = vec![collected.clone(), collected].into_iter().collect();
+        assert_eq!(lines.spans().len(), 4);
+    }
+
+    #[test]
+    fn test_conversions() {
+        let single = StyledTerminalText::new("Hi", TerminalStyle::red());
+        let line = StyledLine::from(single.clone());
+        assert_eq!(line.spans().len(), 1);
+        assert_eq!(line.spans()[0], single);
+        assert_eq!(StyledTerminalText::from(line), single);
+        let mut line = StyledLine::from(single);
+        line.extend(vec![StyledTerminalText::new("!", TerminalStyle::blue())]);
+        let combined = StyledTerminalText::from(line.clone());
+        assert_eq!(combined.plain(), "Hi!");
+        assert_eq!(StyledLine::from(combined), line);
+    }
+}
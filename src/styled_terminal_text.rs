@@ -1,5 +1,12 @@
 use crate::terminal_style::TerminalStyle;
-use std::fmt::{Display, Error, Formatter};
+use crate::styled_line::StyledLine;
+use crate::ansi::strip_ansi;
+use crate::notation::{to_escape_notation, EscapeNotation};
+use crate::sanitize::{sanitize, SanitizePolicy};
+use crate::render::{render_for_policy, render_policy};
+use crate::width::{visible_width, visible_width_with, WidthOptions};
+use std::borrow::Cow;
+use std::fmt::{Debug, Display, Error, Formatter};
 
 // Styled Terminal Text --------------------------------------------------------------------------- /
 
@@ -12,11 +19,12 @@ use std::fmt::{Display, Error, Formatter};
 /// let greeting = StyledTerminalText::new("Hello, World!", TerminalStyle::bright_yellow());
 /// assert_eq!(greeting.output(), "\u{001B}[1;93mHello, World!\u{001B}[0m");
 /// ```
-#[derive(Debug)]
 pub struct StyledTerminalText {
     text: String,
     style: TerminalStyle,
     output: Option<String>,
+    /// Whether styled text nested in the text restores this style when it ends
+    nested: bool,
 }
 
 impl StyledTerminalText {
@@ -26,6 +34,22 @@ impl StyledTerminalText {
         &self.text
     }
 
+    /// Gets the text without any styling. Unlike `text`, this is guaranteed to be free of
+    /// escape sequences, even if some were embedded in the original text.
+    pub fn plain(&self) -> Cow<'_, str> {
+        strip_ansi(&self.text)
+    }
+
+    /// Gets the number of terminal columns the text occupies (see `visible_width`)
+    pub fn visible_width(&self) -> usize {
+        visible_width(&self.text)
+    }
+
+    /// Gets the number of terminal columns the text occupies, using the given options
+    pub fn visible_width_with(&self, options: &WidthOptions) -> usize {
+        visible_width_with(&self.text, options)
+    }
+
     /// Gets the `TerminalStyle` instance
     pub fn style(&self) -> &TerminalStyle {
         &self.style
@@ -40,6 +64,24 @@ impl StyledTerminalText {
         }
     }
 
+    /// Adds padding to the left of the text, so it is right-aligned within the given width
+    /// (see `StyledLine::pad_left`)
+    pub fn pad_left(&self, width: usize, extend_background: bool) -> StyledLine {
+        StyledLine::from(self.clone()).pad_left(width, extend_background)
+    }
+
+    /// Adds padding to the right of the text, so it is left-aligned within the given width
+    /// (see `StyledLine::pad_right`)
+    pub fn pad_right(&self, width: usize, extend_background: bool) -> StyledLine {
+        StyledLine::from(self.clone()).pad_right(width, extend_background)
+    }
+
+    /// Adds padding to both sides of the text, so it is centered within the given width
+    /// (see `StyledLine::center`)
+    pub fn center(&self, width: usize, extend_background: bool) -> StyledLine {
+        StyledLine::from(self.clone()).center(width, extend_background)
+    }
+
     /// Changes text and returns the existing text.
     pub fn change_text_to(&mut self, new_text: &str) -> String {
         let current_text = self.text.clone();
@@ -64,23 +106,93 @@ impl StyledTerminalText {
             text: String::from(text),
             style,
             output: None, // Just temporary
+            nested: false,
         };
         new_instance.update_output();
         new_instance
     }
 
+    /// Creates from a string (that may contain other styled text) and terminal color.
+    /// Unlike `new`, styled text nested in the string restores this style when it ends,
+    /// instead of resetting to no color (see `TerminalStyle::wrap_nested`).
+    ///
+    /// # Examples
+    /// ```
+    /// use terminal_text_styler::{StyledTerminalText, TerminalStyle, highlight_bright_yellow};
+    ///
+    /// let inner = highlight_bright_yellow("World");
+    /// let greeting = StyledTerminalText::new_nested(&format!("Hello, {}!", inner), TerminalStyle::red());
+    /// assert_eq!(greeting.output(), "\u{001B}[0;31mHello, \u{001B}[1;93mWorld\u{001B}[0;31m!\u{001B}[0m");
+    /// ```
+    pub fn new_nested(text: &str, style: TerminalStyle) -> Self {
+        let mut new_instance = Self::new(text, style);
+        new_instance.nested = true;
+        new_instance.update_output();
+        new_instance
+    }
+
+    /// Creates from untrusted text and terminal color, sanitizing the text with the given policy first
+    ///
+    /// # Examples
+    /// ```
+    /// use terminal_text_styler::{StyledTerminalText, TerminalStyle, SanitizePolicy};
+    ///
+    /// let file_name = StyledTerminalText::new_sanitized("evil\u{001B}[2J.txt", TerminalStyle::red(), SanitizePolicy::Escape);
+    /// assert_eq!(file_name.output(), "\u{001B}[0;31mevil␛[2J.txt\u{001B}[0m");
+    /// ```
+    pub fn new_sanitized(text: &str, style: TerminalStyle, policy: SanitizePolicy) -> Self {
+        Self::new(&sanitize(text, policy), style)
+    }
+
     // Private instance methods ------------------------------------------------------------------ /
 
     /// Private method that updates the stored output string
     fn update_output(&mut self) {
-        self.output = Some(self.style.wrap(&self.text));
+        let output = if self.nested {
+            self.style.wrap_nested(&self.text)
+        } else {
+            self.style.wrap(&self.text)
+        };
+        self.output = Some(output);
     }
 }
 
 impl Display for StyledTerminalText {
 
+    /// Writes the output for terminal. Width, fill, alignment and precision (which truncates)
+    /// are measured in terminal columns, so `{:>10}` lines up regardless of escape codes.
+    /// Escape codes follow the render policy (see `RenderPolicy`).
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), Error> {
+        if formatter.width().is_some() || formatter.precision().is_some() {
+            return Display::fmt(&StyledLine::from(self.clone()), formatter);
+        }
+        formatter.write_str(&render_for_policy(self.output(), render_policy()))
+    }
+}
+
+impl Debug for StyledTerminalText {
+
+    /// Shows the style and text with escape codes made visible, like
+    /// `StyledTerminalText { style: "\e[1;93m", text: "Hello, World!" }`
     fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), Error> {
-        write!(formatter, "{}", self.output())
+        let style = to_escape_notation(self.style.command(), EscapeNotation::Short);
+        let text = to_escape_notation(&self.text, EscapeNotation::Short);
+        formatter.debug_struct("StyledTerminalText")
+            .field("style", &format_args!("\"{}\"", style))
+            .field("text", &format_args!("\"{}\"", text))
+            .finish()
+    }
+}
+
+impl Clone for StyledTerminalText {
+
+    fn clone(&self) -> Self {
+        Self {
+            text: self.text.clone(),
+            style: self.style.clone(),
+            output: self.output.clone(),
+            nested: self.nested,
+        }
     }
 }
 
@@ -106,5 +218,19 @@ mod tests {
         assert_eq!(highlighted.output(), "\u{001B}[1;93mHello, World!\u{001B}[0m");
         assert_eq!(format!("{}", highlighted), "\u{001B}[1;93mHello, World!\u{001B}[0m");
     }
+
+    #[test]
+    fn test_debug_output() {
+        let highlighted = StyledTerminalText::new("Hello, World!", TerminalStyle::bright_yellow());
+        assert_eq!(format!("{:?}", highlighted), r#"StyledTerminalText { style: "\e[1;93m", text: "Hello, World!" }"#);
+    }
+
+    #[test]
+    fn test_plain_text() {
+        let nested = format!("Hello, {}!", TerminalStyle::red().wrap("World"));
+        let highlighted = StyledTerminalText::new(&nested, TerminalStyle::bright_yellow());
+        assert_eq!(highlighted.plain(), "Hello, World!");
+        assert_eq!(highlighted.visible_width(), 13);
+    }
 }
 
@@ -0,0 +1,368 @@
+use crate::styled_line::StyledLine;
+use crate::styled_terminal_text::StyledTerminalText;
+use crate::terminal_style::TerminalStyle;
+use crate::detect::ColorLevel;
+use crate::render::{render_policy, write_style, RESET};
+use crate::sgr_state::{is_well_formed, SGRState};
+use std::fmt::{Debug, Display, Error, Formatter};
+
+// Style methods --------------------------------------------------------------------------------- /
+
+/// Declares a method for each color and effect, which adds its SGR codes with `$apply`.
+/// Used for both the `Stylize` trait (to start styling a value) and `Styled` (to keep adding styles).
+macro_rules! style_methods {
+    ($vis:vis $output:ty, $apply:ident) => {
+        style_methods! { @methods $vis $output, $apply,
+            /// Black text
+            black() => [30],
+            /// Red text
+            red() => [31],
+            /// Green text
+            green() => [32],
+            /// Yellow text
+            yellow() => [33],
+            /// Blue text
+            blue() => [34],
+            /// Magenta text
+            magenta() => [35],
+            /// Cyan text
+            cyan() => [36],
+            /// White text
+            white() => [37],
+            /// Bright black (gray) text
+            bright_black() => [90],
+            /// Bright red text
+            bright_red() => [91],
+            /// Bright green text
+            bright_green() => [92],
+            /// Bright yellow text
+            bright_yellow() => [93],
+            /// Bright blue text
+            bright_blue() => [94],
+            /// Bright magenta text
+            bright_magenta() => [95],
+            /// Bright cyan text
+            bright_cyan() => [96],
+            /// Bright white text
+            bright_white() => [97],
+            /// Text in an ANSI 256-color
+            color(index: u8) => [38, 5, index],
+            /// Text in a 24-bit color
+            rgb(red: u8, green: u8, blue: u8) => [38, 2, red, green, blue],
+            /// Black background
+            on_black() => [40],
+            /// Red background
+            on_red() => [41],
+            /// Green background
+            on_green() => [42],
+            /// Yellow background
+            on_yellow() => [43],
+            /// Blue background
+            on_blue() => [44],
+            /// Magenta background
+            on_magenta() => [45],
+            /// Cyan background
+            on_cyan() => [46],
+            /// White background
+            on_white() => [47],
+            /// Bright black (gray) background
+            on_bright_black() => [100],
+            /// Bright red background
+            on_bright_red() => [101],
+            /// Bright green background
+            on_bright_green() => [102],
+            /// Bright yellow background
+            on_bright_yellow() => [103],
+            /// Bright blue background
+            on_bright_blue() => [104],
+            /// Bright magenta background
+            on_bright_magenta() => [105],
+            /// Bright cyan background
+            on_bright_cyan() => [106],
+            /// Bright white background
+            on_bright_white() => [107],
+            /// Background in an ANSI 256-color
+            on_color(index: u8) => [48, 5, index],
+            /// Background in a 24-bit color
+            on_rgb(red: u8, green: u8, blue: u8) => [48, 2, red, green, blue],
+            /// Bold text
+            bold() => [1],
+            /// Faint (dim) text
+            faint() => [2],
+            /// Italic text
+            italic() => [3],
+            /// Underlined text
+            underline() => [4],
+            /// Slowly blinking text
+            blink() => [5],
+            /// Text with swapped foreground and background colors
+            inverse() => [7],
+            /// Hidden text
+            hidden() => [8],
+            /// Crossed-out text
+            crossed_out() => [9],
+        }
+    };
+    (@methods $vis:vis $output:ty, $apply:ident, $($(#[$doc:meta])* $name:ident($($arg:ident: $type:ty),*) => [$($code:expr),*],)*) => {
+        $(
+            $(#[$doc])*
+            $vis fn $name(self $(, $arg: $type)*) -> $output {
+                self.$apply(&[$($code),*])
+            }
+        )*
+    };
+}
+
+// Stylize --------------------------------------------------------------------------------------- /
+
+/// Adds style methods to strings, numbers, paths, errors and anything else that can be displayed,
+/// so they can be styled inline without first converting them to a `String`.
+/// Each method returns a `Styled` value, which can be styled further and is only formatted
+/// when it is displayed.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{Stylize, TerminalStyle};
+///
+/// assert_eq!(format!("{}", "error".red().bold()), "\u{001B}[31;1merror\u{001B}[0m");
+/// assert_eq!(format!("{} files", 42.on_blue()), "\u{001B}[44m42\u{001B}[0m files");
+/// assert_eq!("note".style(&TerminalStyle::bright_yellow()).to_string(), "\u{001B}[1;93mnote\u{001B}[0m");
+/// ```
+pub trait Stylize: Sized {
+
+    /// Styles the value with the given style (which is borrowed, not copied)
+    fn style(self, style: &TerminalStyle) -> Styled<'_, Self> {
+        Styled::borrowed(self, style)
+    }
+
+    /// Styles the value with raw SGR codes
+    fn with_codes(self, codes: &[u8]) -> Styled<'static, Self> {
+        Styled { value: self, codes: StyleCodes::from(codes) }
+    }
+
+    style_methods!(Styled<'static, Self>, with_codes);
+}
+
+impl<T: Display> Stylize for T {}
+
+// Styled ---------------------------------------------------------------------------------------- /
+
+/// A value with a style, which is displayed with the escape codes of the style around it.
+/// Width, fill, alignment and precision are passed on to the value (so padding is styled too).
+///
+/// Unlike `StyledTerminalText`, this doesn't allocate: the value is only formatted when it is
+/// displayed (straight into the formatter), and the style is either borrowed (see `borrowed`) or,
+/// for the style methods, kept as a short list of codes. Convert it to a `StyledTerminalText` to keep an owned copy.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{Styled, Stylize, TerminalStyle, StyledTerminalText};
+///
+/// let green = TerminalStyle::from(vec![32]);
+/// let count = Styled::borrowed(3, &green);
+/// assert_eq!(format!("[{:>3}]", count.underline()), "[\u{001B}[32;4m  3\u{001B}[0m]");
+/// assert_eq!(StyledTerminalText::from(Styled::new(3, green)).output(), "\u{001B}[32m3\u{001B}[0m");
+/// ```
+#[derive(Clone)]
+pub struct Styled<'a, T> {
+    value: T,
+    codes: StyleCodes<'a>,
+}
+
+impl<'a, T> Styled<'a, T> {
+
+    /// Gets the value being styled
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Gets the SGR codes of the style
+    pub fn codes(&self) -> &[u8] {
+        self.codes.codes()
+    }
+
+    /// Gets the style of the value
+    pub fn to_style(&self) -> TerminalStyle {
+        match &self.codes {
+            StyleCodes::Borrowed(style) => (*style).clone(),
+            StyleCodes::Owned(style) => style.clone(),
+            StyleCodes::Inline(..) => TerminalStyle::from(self.codes().to_vec()),
+        }
+    }
+
+    /// Adds a style on top of the current style. Unlike `Stylize::style`, a reset at the start of the
+    /// style is left out, so `"a".bold().style(&TerminalStyle::red())` is bold and red.
+    /// The styles are merged, so codes they both set aren't repeated.
+    pub fn style(mut self, style: &TerminalStyle) -> Self {
+        let current = self.codes();
+        let overlay: Vec<u8> = style.codes().iter().copied().skip_while(|code| *code == 0).collect();
+        if !is_well_formed(current) || !is_well_formed(&overlay) {
+            // Malformed colors can't be merged without losing codes
+            return self.with_codes(&overlay);
+        }
+        let reset = current.first() == Some(&0);
+        let mut state = SGRState::new();
+        state.apply(current);
+        state.apply(&overlay);
+        // The merged codes start with a reset, which is only kept if the current style had one
+        let merged = state.to_style();
+        let start = if reset { 0 } else { 1 };
+        self.codes = StyleCodes::from(&merged.codes()[start..]);
+        self
+    }
+
+    /// Adds raw SGR codes to the current style
+    pub fn with_codes(mut self, codes: &[u8]) -> Self {
+        let current = self.codes();
+        self.codes = if current.len() + codes.len() <= INLINE_CODES {
+            let mut inline = [0; INLINE_CODES];
+            inline[..current.len()].copy_from_slice(current);
+            inline[current.len()..current.len() + codes.len()].copy_from_slice(codes);
+            StyleCodes::Inline(inline, current.len() + codes.len())
+        } else {
+            StyleCodes::Owned(TerminalStyle::from([current, codes].concat()))
+        };
+        self
+    }
+
+    style_methods!(pub Self, with_codes);
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Creates from a value and a terminal style
+    pub fn new(value: T, style: TerminalStyle) -> Self {
+        Styled { value, codes: StyleCodes::Owned(style) }
+    }
+
+    /// Creates from a value and a borrowed terminal style, which isn't copied
+    pub fn borrowed(value: T, style: &'a TerminalStyle) -> Self {
+        Styled { value, codes: StyleCodes::Borrowed(style) }
+    }
+}
+
+impl<'a, T: Display> Display for Styled<'a, T> {
+
+    /// Writes the value in its style, following the render policy (see `RenderPolicy`)
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), Error> {
+        let policy = render_policy();
+        let level = policy.color_level();
+        if level == ColorLevel::None {
+            return self.value.fmt(formatter);
+        }
+        let command = match &self.codes {
+            StyleCodes::Borrowed(style) => Some(style.command()),
+            StyleCodes::Owned(style) => Some(style.command()),
+            StyleCodes::Inline(..) => None,
+        };
+        write_style(formatter, self.codes(), command, level, policy.terminfo())?;
+        self.value.fmt(formatter)?;
+        formatter.write_str(RESET)
+    }
+}
+
+impl<'a, T: Debug> Debug for Styled<'a, T> {
+
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), Error> {
+        formatter.debug_struct("Styled")
+            .field("value", &self.value)
+            .field("codes", &self.codes())
+            .finish()
+    }
+}
+
+impl<'a, T: PartialEq> PartialEq for Styled<'a, T> {
+
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value && self.codes() == other.codes()
+    }
+}
+
+impl<'a, T: Eq> Eq for Styled<'a, T> {}
+
+impl<'a, T: Display> From<Styled<'a, T>> for StyledTerminalText {
+
+    /// Makes an owned copy of the styled value
+    fn from(styled: Styled<'a, T>) -> Self {
+        StyledTerminalText::new(&styled.value.to_string(), styled.to_style())
+    }
+}
+
+impl<'a, T: Display> From<Styled<'a, T>> for StyledLine {
+
+    /// Makes an owned line from the styled value, so it can be joined or added to other styled text
+    fn from(styled: Styled<'a, T>) -> Self {
+        StyledLine::from(StyledTerminalText::from(styled))
+    }
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Number of codes kept without allocating (enough for a 24-bit foreground and background)
+const INLINE_CODES: usize = 12;
+
+/// The codes of a style, borrowed, kept inline or (when there are many) owned
+#[derive(Clone)]
+enum StyleCodes<'a> {
+    Borrowed(&'a TerminalStyle),
+    Inline([u8; INLINE_CODES], usize),
+    Owned(TerminalStyle),
+}
+
+impl<'a> StyleCodes<'a> {
+
+    fn codes(&self) -> &[u8] {
+        match self {
+            StyleCodes::Borrowed(style) => style.codes(),
+            StyleCodes::Inline(codes, length) => &codes[..*length],
+            StyleCodes::Owned(style) => style.codes(),
+        }
+    }
+}
+
+impl<'a> From<&[u8]> for StyleCodes<'a> {
+
+    fn from(codes: &[u8]) -> Self {
+        if codes.len() <= INLINE_CODES {
+            let mut inline = [0; INLINE_CODES];
+            inline[..codes.len()].copy_from_slice(codes);
+            StyleCodes::Inline(inline, codes.len())
+        } else {
+            StyleCodes::Owned(TerminalStyle::from(codes.to_vec()))
+        }
+    }
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_stylize() {
+        assert_eq!("a".green().to_string(), "\u{001B}[32ma\u{001B}[0m");
+        assert_eq!(String::from("a").color(183).on_rgb(1, 2, 3).to_string(), "\u{001B}[38;5;183;48;2;1;2;3ma\u{001B}[0m");
+        assert_eq!(Path::new("/tmp").display().italic().to_string(), "\u{001B}[3m/tmp\u{001B}[0m");
+        let red = TerminalStyle::red();
+        let styled = 1.5.style(&red).bold();
+        assert_eq!(styled.codes(), &[0, 31, 1]);
+        assert_eq!(format!("{:.2}", styled.value()), "1.50");
+    }
+
+    #[test]
+    fn test_styled_formatting() {
+        let styled = "abc".bold().style(&TerminalStyle::bright_red());
+        assert_eq!(format!("{:-<5.2}", styled), "\u{001B}[1;91mab---\u{001B}[0m");
+        assert_eq!("a".red().bold().style(&TerminalStyle::blue()).codes(), &[1, 34]);
+        assert_eq!("a".style(&TerminalStyle::red()).style(&TerminalStyle::red()).codes(), &[0, 31]);
+        let bold = TerminalStyle::from(vec![1]);
+        assert_eq!("x".underline().with_codes(&[58, 5, 196]).style(&bold).codes(), &[1, 4, 58, 5, 196]);
+        assert_eq!("x".with_codes(&[53, 38, 5]).style(&bold).codes(), &[53, 38, 5, 1]);
+        let many = "x".rgb(1, 2, 3).on_rgb(4, 5, 6).bold().italic();
+        assert_eq!(many.codes(), &[38, 2, 1, 2, 3, 48, 2, 4, 5, 6, 1, 3]);
+        assert_eq!(many.underline().to_string(), "\u{001B}[38;2;1;2;3;48;2;4;5;6;1;3;4mx\u{001B}[0m");
+        assert_eq!("x".red(), "x".with_codes(&[31]));
+        assert_eq!(StyledLine::join(vec![1.red(), 2.red()], "+").plain(), "1+2");
+    }
+}
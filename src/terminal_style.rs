@@ -1,6 +1,8 @@
 use std::fmt::{Display, Formatter, Error};
 use crate::{SGREffect, ANSIForegroundColor, ANSIBackgroundColor};
 use crate::traits::Coded;
+use crate::nesting::repair_nested;
+use crate::render::{render_policy, write_style};
 
 // Terminal Style -------------------------------------------------------------------------------- /
 
@@ -45,7 +47,7 @@ impl TerminalStyle {
 
     /// Gets a list of all applied SGR effect styles
     pub fn styles(&self) -> Vec<SGREffect> {
-        self.codes.iter().map(|code| SGREffect::from(code)).collect()
+        self.codes.iter().map(SGREffect::from).collect()
     }
 
     /// Looks up the foreground color (Note: This is not supported for ANSI 256-colors and will return `None`)
@@ -95,22 +97,16 @@ impl TerminalStyle {
         let mut codes: Vec<u8> = effects.iter().map(|effect| effect.code()).collect();
         if let Some(foreground) = foreground {
             codes.push(foreground.code());
-            match foreground {
-                ANSIForegroundColor::ANSI256(ansi256) => {
-                    codes.push(5);
-                    codes.push(ansi256);
-                }
-                _ => ()
+            if let ANSIForegroundColor::ANSI256(ansi256) = foreground {
+                codes.push(5);
+                codes.push(ansi256);
             }
         }
         if let Some(background) = background {
             codes.push(background.code());
-            match background {
-                ANSIBackgroundColor::ANSI256(ansi256) => {
-                    codes.push(5);
-                    codes.push(ansi256);
-                }
-                _ => ()
+            if let ANSIBackgroundColor::ANSI256(ansi256) = background {
+                codes.push(5);
+                codes.push(ansi256);
             }
         }
         Self::from(codes)
@@ -134,10 +130,26 @@ impl TerminalStyle {
         format!("{}{}{}", start, text, end)
     }
 
+    /// Wraps given text like `wrap`, but any styled text nested inside (ending with a reset)
+    /// restores this style when it ends, instead of resetting to no color.
+    ///
+    /// # Examples
+    /// ```
+    /// use terminal_text_styler::TerminalStyle;
+    ///
+    /// let red = TerminalStyle::red();
+    /// let bold = TerminalStyle::from(vec![1]);
+    /// let output = red.wrap_nested(&format!("a {} b", bold.wrap("x")));
+    /// assert_eq!(output, "\u{001B}[0;31ma \u{001B}[1mx\u{001B}[0;31m b\u{001B}[0m");
+    /// ```
+    pub fn wrap_nested(&self, text: &str) -> String {
+        repair_nested(&self.wrap(text))
+    }
+
     // Private instance methods ------------------------------------------------------------------ /
 
     /// This formats the ANSI escape code string that switches the terminal color.
-    fn make_command(codes: &Vec<u8>) -> String {
+    fn make_command(codes: &[u8]) -> String {
         let code_strings: Vec<String> = codes.iter().map(|code| code.to_string()).collect();
         let code = code_strings.join(";");
         let beginning = "\u{001B}[";
@@ -147,9 +159,10 @@ impl TerminalStyle {
 
 impl Display for TerminalStyle {
 
+    /// Writes the escape code, following the render policy (see `RenderPolicy`)
     fn fmt(&self, formatter: &mut Formatter<'_>) -> Result<(), Error> {
-        let command = self.command();
-        write!(formatter, "{}", command)
+        let policy = render_policy();
+        write_style(formatter, &self.codes, Some(self.command()), policy.color_level(), policy.terminfo())
     }
 }
 
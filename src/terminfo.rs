@@ -0,0 +1,434 @@
+use crate::detect::ColorLevel;
+use crate::enums::srg_effect::SGREffect;
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::env;
+use std::error::Error;
+use std::fmt::{self, Display, Formatter};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Magic number of compiled entries with 16-bit numbers
+const LEGACY_MAGIC: i16 = 0o432;
+/// Magic number of compiled entries with 32-bit numbers
+const EXTENDED_NUMBERS_MAGIC: i16 = 0o1036;
+
+/// Indices of the standard numeric capabilities that can be looked up by name
+const NUMBERS: &[(&str, usize)] = &[("cols", 0), ("it", 1), ("lines", 2), ("colors", 13), ("pairs", 14), ("ncv", 15)];
+
+/// Indices of the standard string capabilities that can be looked up by name
+const STRINGS: &[(&str, usize)] = &[
+    ("blink", 26), ("bold", 27), ("dim", 30), ("invis", 32), ("rev", 34), ("smso", 35), ("smul", 36),
+    ("sgr0", 39), ("sitm", 311), ("ritm", 312), ("setaf", 359), ("setab", 360),
+];
+
+// Terminfo Error -------------------------------------------------------------------------------- /
+
+/// Describes why a terminfo entry couldn't be loaded.
+#[derive(Debug)]
+pub enum TerminfoError {
+    /// No entry for the terminal was found in any of the searched directories
+    NotFound { term: String },
+    /// The entry couldn't be read
+    Io(io::Error),
+    /// The entry is not a valid compiled terminfo entry
+    Invalid { reason: &'static str },
+}
+
+impl Display for TerminfoError {
+
+    fn fmt(&self, formatter: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            TerminfoError::NotFound { term } => write!(formatter, "no terminfo entry found for '{}'", term),
+            TerminfoError::Io(error) => write!(formatter, "could not read terminfo entry: {}", error),
+            TerminfoError::Invalid { reason } => write!(formatter, "invalid terminfo entry: {}", reason),
+        }
+    }
+}
+
+impl Error for TerminfoError {
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            TerminfoError::Io(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for TerminfoError {
+
+    fn from(error: io::Error) -> Self {
+        TerminfoError::Io(error)
+    }
+}
+
+// Terminfo -------------------------------------------------------------------------------------- /
+
+/// The capabilities of a terminal, read from its compiled terminfo entry (in the legacy format or
+/// the format with 32-bit numbers, including user-defined capabilities like `Tc` and `RGB`).
+///
+/// # Examples
+/// ```no_run
+/// use terminal_text_styler::{Terminfo, SGREffect};
+///
+/// let terminfo = Terminfo::from_name("xterm-256color").unwrap();
+/// assert_eq!(terminfo.colors(), Some(256));
+/// assert!(terminfo.supports_effect(&SGREffect::Italic));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Terminfo {
+    names: Vec<String>,
+    numbers: Vec<Option<i32>>,
+    strings: Vec<Option<Vec<u8>>>,
+    extended_booleans: HashMap<String, bool>,
+    extended_numbers: HashMap<String, i32>,
+    extended_strings: HashMap<String, Vec<u8>>,
+}
+
+impl Terminfo {
+
+    /// Gets the names of the terminal (the first is the primary name, the last is usually a description)
+    pub fn names(&self) -> &[String] {
+        &self.names
+    }
+
+    /// Whether a user-defined boolean capability (like `Tc`) is set
+    pub fn flag(&self, name: &str) -> bool {
+        self.extended_booleans.get(name).copied().unwrap_or(false)
+    }
+
+    /// Gets a numeric capability, like `colors`. Standard capabilities are looked up by their short
+    /// name (`cols`, `it`, `lines`, `colors`, `pairs` and `ncv` are supported), as well as any
+    /// user-defined ones.
+    pub fn number(&self, name: &str) -> Option<i32> {
+        match NUMBERS.iter().find(|(capability, _)| *capability == name) {
+            Some((_, index)) => self.numbers.get(*index).copied().flatten(),
+            None => self.extended_numbers.get(name).copied(),
+        }
+    }
+
+    /// Gets a string capability, like `setaf`. Standard capabilities are looked up by their short
+    /// name (`blink`, `bold`, `dim`, `invis`, `rev`, `smso`, `smul`, `sgr0`, `sitm`, `ritm`, `setaf`
+    /// and `setab` are supported), as well as any user-defined ones (like `smxx`).
+    pub fn string(&self, name: &str) -> Option<&[u8]> {
+        match STRINGS.iter().find(|(capability, _)| *capability == name) {
+            Some((_, index)) => self.strings.get(*index).and_then(|value| value.as_deref()),
+            None => self.extended_strings.get(name).map(Vec::as_slice),
+        }
+    }
+
+    /// Gets the number of colors the terminal supports (`colors`)
+    pub fn colors(&self) -> Option<i32> {
+        self.number("colors")
+    }
+
+    /// Gets the command that sets the foreground color (`setaf`)
+    pub fn setaf(&self) -> Option<&[u8]> {
+        self.string("setaf")
+    }
+
+    /// Gets the command that sets the background color (`setab`)
+    pub fn setab(&self) -> Option<&[u8]> {
+        self.string("setab")
+    }
+
+    /// Gets the command that starts italic text (`sitm`)
+    pub fn sitm(&self) -> Option<&[u8]> {
+        self.string("sitm")
+    }
+
+    /// Gets the command that starts underlined text (`smul`)
+    pub fn smul(&self) -> Option<&[u8]> {
+        self.string("smul")
+    }
+
+    /// Whether the terminal supports 24-bit colors, as told by the `Tc` or `RGB` extensions
+    pub fn has_true_color(&self) -> bool {
+        self.flag("Tc") || self.flag("RGB") || self.extended_numbers.contains_key("RGB") || self.extended_strings.contains_key("RGB")
+    }
+
+    /// Gets the color level the terminal supports
+    pub fn color_level(&self) -> ColorLevel {
+        match self.colors() {
+            _ if self.has_true_color() => ColorLevel::TrueColor,
+            Some(colors) if colors >= 0x1000000 => ColorLevel::TrueColor,
+            Some(colors) if colors >= 256 => ColorLevel::Ansi256,
+            Some(colors) if colors >= 8 => ColorLevel::Ansi16,
+            _ => ColorLevel::None,
+        }
+    }
+
+    /// Whether the terminal has a capability for the effect, so it is safe to emit
+    pub fn supports_effect(&self, effect: &SGREffect) -> bool {
+        let capability = match effect {
+            SGREffect::Normal => return true,
+            SGREffect::Bold => "bold",
+            SGREffect::Faint => "dim",
+            SGREffect::Italic => "sitm",
+            SGREffect::Underline => "smul",
+            SGREffect::SlowBlink | SGREffect::RapidBlink => "blink",
+            SGREffect::CrossedOut => "smxx",
+            SGREffect::ByCode(7) => "rev",
+            SGREffect::ByCode(8) => "invis",
+            SGREffect::ByCode(_) => return false,
+        };
+        self.string(capability).is_some()
+    }
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Loads the entry for the terminal named by the `TERM` environment variable
+    pub fn from_env() -> Result<Self, TerminfoError> {
+        let term = env::var("TERM").map_err(|_| TerminfoError::NotFound { term: String::new() })?;
+        Self::from_name(&term)
+    }
+
+    /// Loads the entry for the named terminal, searching `$TERMINFO`, `~/.terminfo`, the
+    /// directories in `$TERMINFO_DIRS`, `/etc/terminfo`, `/lib/terminfo` and `/usr/share/terminfo`
+    pub fn from_name(term: &str) -> Result<Self, TerminfoError> {
+        let first = match term.chars().next() {
+            Some(first) if !term.contains(['/', '\\']) && term != "." && term != ".." => first,
+            _ => return Err(TerminfoError::NotFound { term: term.to_string() }),
+        };
+        for directory in search_directories() {
+            // Entries are in a directory named by their first character (or its hex code on macOS)
+            for subdirectory in [first.to_string(), format!("{:x}", first as u32)] {
+                let path = directory.join(subdirectory).join(term);
+                if path.is_file() {
+                    return Self::from_path(&path);
+                }
+            }
+        }
+        Err(TerminfoError::NotFound { term: term.to_string() })
+    }
+
+    /// Loads a compiled entry from a file
+    pub fn from_path(path: &Path) -> Result<Self, TerminfoError> {
+        Self::from_bytes(&fs::read(path)?)
+    }
+
+    /// Parses a compiled entry
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, TerminfoError> {
+        let mut reader = Reader { bytes, position: 0 };
+        let number_size = match reader.i16()? {
+            LEGACY_MAGIC => 2,
+            EXTENDED_NUMBERS_MAGIC => 4,
+            _ => return Err(TerminfoError::Invalid { reason: "unknown magic number" }),
+        };
+        let names_size = reader.count()?;
+        let boolean_count = reader.count()?;
+        let number_count = reader.count()?;
+        let string_count = reader.count()?;
+        let table_size = reader.count()?;
+        let names = reader.take(names_size)?;
+        let names = String::from_utf8_lossy(names.split(|byte| *byte == 0).next().unwrap_or_default());
+        let mut terminfo = Terminfo {
+            names: names.split('|').map(String::from).collect(),
+            ..Self::default()
+        };
+        // Standard flags aren't needed by any of the lookups
+        reader.take(boolean_count)?;
+        reader.align();
+        terminfo.numbers = (0..number_count)
+            .map(|_| reader.number(number_size).map(|number| Some(number).filter(|number| *number >= 0)))
+            .collect::<Result<_, _>>()?;
+        let offsets = (0..string_count).map(|_| reader.i16()).collect::<Result<Vec<_>, _>>()?;
+        let table = reader.take(table_size)?;
+        terminfo.strings = offsets.iter().map(|offset| string_at(table, *offset).map(<[u8]>::to_vec)).collect();
+        reader.align();
+        if reader.position < bytes.len() {
+            terminfo.read_extended(&mut reader, number_size)?;
+        }
+        Ok(terminfo)
+    }
+
+    // Private instance methods ------------------------------------------------------------------ /
+
+    /// Reads the user-defined capabilities that follow the standard ones
+    fn read_extended(&mut self, reader: &mut Reader<'_>, number_size: usize) -> Result<(), TerminfoError> {
+        let boolean_count = reader.count()?;
+        let number_count = reader.count()?;
+        let string_count = reader.count()?;
+        let _item_count = reader.count()?;
+        let table_size = reader.count()?;
+        let booleans: Vec<bool> = reader.take(boolean_count)?.iter().map(|value| *value == 1).collect();
+        reader.align();
+        let numbers = (0..number_count).map(|_| reader.number(number_size)).collect::<Result<Vec<_>, _>>()?;
+        let offsets = (0..string_count).map(|_| reader.i16()).collect::<Result<Vec<_>, _>>()?;
+        let name_offsets = (0..boolean_count + number_count + string_count)
+            .map(|_| reader.i16())
+            .collect::<Result<Vec<_>, _>>()?;
+        let table = reader.take(table_size)?;
+        // The names follow the string values in the table
+        let values_end = offsets.iter()
+            .filter_map(|offset| string_at(table, *offset).map(|value| *offset as usize + value.len() + 1))
+            .max()
+            .unwrap_or(0);
+        let names_table = table.get(values_end..).unwrap_or_default();
+        let names = name_offsets.iter()
+            .map(|offset| string_at(names_table, *offset).map(|name| String::from_utf8_lossy(name).into_owned()))
+            .collect::<Option<Vec<_>>>()
+            .ok_or(TerminfoError::Invalid { reason: "missing extended capability name" })?;
+        let (boolean_names, rest) = names.split_at(boolean_count);
+        let (number_names, string_names) = rest.split_at(number_count);
+        self.extended_booleans.extend(boolean_names.iter().cloned().zip(booleans));
+        self.extended_numbers.extend(
+            number_names.iter().cloned().zip(numbers).filter(|(_, value)| *value >= 0)
+        );
+        self.extended_strings.extend(
+            string_names.iter().cloned().zip(offsets)
+                .filter_map(|(name, offset)| string_at(table, offset).map(|value| (name, value.to_vec())))
+        );
+        Ok(())
+    }
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Reads little-endian values from a compiled entry
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> Reader<'a> {
+
+    fn take(&mut self, length: usize) -> Result<&'a [u8], TerminfoError> {
+        let bytes = self.bytes.get(self.position..self.position + length)
+            .ok_or(TerminfoError::Invalid { reason: "unexpected end of entry" })?;
+        self.position += length;
+        Ok(bytes)
+    }
+
+    fn i16(&mut self) -> Result<i16, TerminfoError> {
+        let bytes = self.take(2)?;
+        Ok(i16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    /// Reads a count or size from a header (which can't be negative)
+    fn count(&mut self) -> Result<usize, TerminfoError> {
+        let count = self.i16()?;
+        usize::try_from(count).map_err(|_| TerminfoError::Invalid { reason: "negative size in header" })
+    }
+
+    fn number(&mut self, size: usize) -> Result<i32, TerminfoError> {
+        if size == 2 {
+            return self.i16().map(i32::from);
+        }
+        let bytes = self.take(4)?;
+        Ok(i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    /// Skips a byte if needed, so the next value starts at an even position
+    fn align(&mut self) {
+        if self.position % 2 == 1 {
+            self.position += 1;
+        }
+    }
+}
+
+/// Gets the NUL-terminated string at an offset of a string table (negative offsets are missing
+/// or cancelled capabilities)
+fn string_at(table: &[u8], offset: i16) -> Option<&[u8]> {
+    let rest = table.get(usize::try_from(offset).ok()?..)?;
+    let end = rest.iter().position(|byte| *byte == 0)?;
+    Some(&rest[..end])
+}
+
+/// Gets the directories searched for terminfo entries, in order
+fn search_directories() -> Vec<PathBuf> {
+    let mut directories = Vec::new();
+    if let Some(directory) = env::var_os("TERMINFO") {
+        directories.push(PathBuf::from(directory));
+    }
+    if let Some(home) = env::var_os("HOME") {
+        directories.push(Path::new(&home).join(".terminfo"));
+    }
+    if let Some(list) = env::var_os("TERMINFO_DIRS") {
+        // An empty entry stands for the default directory
+        directories.extend(env::split_paths(&list).filter(|directory| !directory.as_os_str().is_empty()));
+    }
+    directories.extend(["/etc/terminfo", "/lib/terminfo", "/usr/share/terminfo"].iter().map(PathBuf::from));
+    directories
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Compiles an entry with `colors`, `setaf` and `smul`, and optionally a `Tc` extension
+    fn compile(magic: i16, colors: i32, extended: bool) -> Vec<u8> {
+        let number_size = if magic == LEGACY_MAGIC { 2 } else { 4 };
+        let names = b"test|Test terminal\0";
+        let setaf = b"\x1b[3%p1%dm\0";
+        let smul = b"\x1b[4m\0";
+        let mut strings = vec![-1i16; 361];
+        strings[36] = 0;
+        strings[359] = smul.len() as i16;
+        let mut bytes = Vec::new();
+        let header = [magic, names.len() as i16, 2, 14, strings.len() as i16, (setaf.len() + smul.len()) as i16];
+        header.iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+        bytes.extend_from_slice(names);
+        bytes.extend_from_slice(&[0, 1]);
+        if bytes.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        for index in 0..14 {
+            let value = if index == 13 { colors } else { -1 };
+            bytes.extend_from_slice(&value.to_le_bytes()[..number_size]);
+        }
+        strings.iter().for_each(|offset| bytes.extend_from_slice(&offset.to_le_bytes()));
+        bytes.extend_from_slice(smul);
+        bytes.extend_from_slice(setaf);
+        if extended {
+            if bytes.len() % 2 == 1 {
+                bytes.push(0);
+            }
+            // One flag (`Tc`) and one string (`smxx`)
+            let table = b"\x1b[9m\0Tc\0smxx\0";
+            [1i16, 0, 1, 3, table.len() as i16].iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+            bytes.extend_from_slice(&[1, 0]);
+            [0i16, 0, 3].iter().for_each(|value| bytes.extend_from_slice(&value.to_le_bytes()));
+            bytes.extend_from_slice(table);
+        }
+        bytes
+    }
+
+    #[test]
+    fn test_legacy_format() {
+        let terminfo = Terminfo::from_bytes(&compile(LEGACY_MAGIC, 8, false)).unwrap();
+        assert_eq!(terminfo.names(), &["test", "Test terminal"]);
+        assert_eq!(terminfo.colors(), Some(8));
+        assert_eq!(terminfo.number("lines"), None);
+        assert_eq!(terminfo.setaf(), Some(&b"\x1b[3%p1%dm"[..]));
+        assert_eq!(terminfo.smul(), Some(&b"\x1b[4m"[..]));
+        assert_eq!(terminfo.sitm(), None);
+        assert!(terminfo.supports_effect(&SGREffect::Underline));
+        assert!(!terminfo.supports_effect(&SGREffect::Italic));
+        assert_eq!(terminfo.color_level(), ColorLevel::Ansi16);
+    }
+
+    #[test]
+    fn test_extended_format() {
+        let terminfo = Terminfo::from_bytes(&compile(EXTENDED_NUMBERS_MAGIC, 0x1000000, true)).unwrap();
+        assert_eq!(terminfo.colors(), Some(0x1000000));
+        assert!(terminfo.flag("Tc"));
+        assert!(terminfo.has_true_color());
+        assert_eq!(terminfo.string("smxx"), Some(&b"\x1b[9m"[..]));
+        assert!(terminfo.supports_effect(&SGREffect::CrossedOut));
+        assert_eq!(terminfo.color_level(), ColorLevel::TrueColor);
+    }
+
+    #[test]
+    fn test_invalid_entries() {
+        assert!(matches!(Terminfo::from_bytes(b"\x1a\x01\x00"), Err(TerminfoError::Invalid { .. })));
+        assert!(matches!(Terminfo::from_bytes(b"nope"), Err(TerminfoError::Invalid { .. })));
+        let truncated = compile(LEGACY_MAGIC, 8, false);
+        assert!(Terminfo::from_bytes(&truncated[..truncated.len() - 4]).is_err());
+        assert!(matches!(Terminfo::from_name("../passwd"), Err(TerminfoError::NotFound { .. })));
+    }
+}
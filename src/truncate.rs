@@ -0,0 +1,166 @@
+use crate::styled_line::{PlainLine, StyledLine};
+use crate::styled_terminal_text::StyledTerminalText;
+use crate::terminal_style::TerminalStyle;
+use crate::width::WidthOptions;
+use unicode_segmentation::UnicodeSegmentation;
+
+// Truncate Options ------------------------------------------------------------------------------ /
+
+/// Where text is cut when it is too wide.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TruncatePosition {
+    /// Keeps the end of the text (`…xt`)
+    Start,
+    /// Keeps the start and end of the text (`t…t`)
+    Middle,
+    /// Keeps the start of the text (`te…`)
+    End,
+}
+
+/// Options used to truncate styled text to a given width.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{TruncateOptions, TruncatePosition, StyledTerminalText, TerminalStyle};
+///
+/// let options = TruncateOptions {
+///     position: TruncatePosition::Middle,
+///     ellipsis: StyledTerminalText::new("...", TerminalStyle::bright_black()),
+///     ..TruncateOptions::new(20)
+/// };
+/// assert_eq!(options.width, 20);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TruncateOptions {
+    /// Maximum number of columns (including the ellipsis)
+    pub width: usize,
+    /// Where the text is cut
+    pub position: TruncatePosition,
+    /// Placed where the text was cut (defaults to an un-styled `…`)
+    pub ellipsis: StyledTerminalText,
+    /// Options used to measure the width of text
+    pub width_options: WidthOptions,
+}
+
+impl TruncateOptions {
+
+    /// Creates options for the given width, cutting the end of the text and adding `…`
+    pub fn new(width: usize) -> Self {
+        TruncateOptions {
+            width,
+            position: TruncatePosition::End,
+            ellipsis: StyledTerminalText::new("…", TerminalStyle::no_color()),
+            width_options: WidthOptions::default(),
+        }
+    }
+}
+
+// Truncate -------------------------------------------------------------------------------------- /
+
+/// Cuts styled text to fit in the given width, adding an ellipsis where it was cut.
+/// Text that already fits is returned as it is. Wide characters are never split (so the result
+/// may be a column narrower than the width), and each style ends with the text it belongs to.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{truncate_styled, TruncateOptions, StyledLine, TerminalStyle};
+///
+/// let mut line = StyledLine::new();
+/// line.push_styled("error", TerminalStyle::red());
+/// line.push_str(": something went wrong");
+/// let truncated = truncate_styled(&line, &TruncateOptions::new(10));
+/// assert_eq!(truncated.plain(), "error: so…");
+/// assert_eq!(truncated.output(), "\u{001B}[0;31merror\u{001B}[0m: so…");
+/// ```
+pub fn truncate_styled(line: &StyledLine, options: &TruncateOptions) -> StyledLine {
+    let source = PlainLine::new(line);
+    let graphemes: Vec<(usize, &str)> = source.text.grapheme_indices(true).collect();
+    let widths: Vec<usize> = graphemes.iter().map(|(_, grapheme)| options.width_options.grapheme_width(grapheme)).collect();
+    let total: usize = widths.iter().sum();
+    if total <= options.width {
+        return line.clone();
+    }
+    let ellipsis_width = options.ellipsis.visible_width_with(&options.width_options);
+    let (ellipsis, available) = if ellipsis_width <= options.width {
+        (Some(&options.ellipsis), options.width - ellipsis_width)
+    } else {
+        (None, options.width)
+    };
+    let (head, tail) = match options.position {
+        TruncatePosition::Start => (0, available),
+        TruncatePosition::Middle => (available - available / 2, available / 2),
+        TruncatePosition::End => (available, 0),
+    };
+    // Byte offsets where the kept head ends and the kept tail starts
+    let head_end = fitting_count(widths.iter(), head);
+    let tail_count = fitting_count(widths.iter().rev(), tail);
+    let offset = |index: usize| graphemes.get(index).map(|(offset, _)| *offset).unwrap_or(source.text.len());
+    let mut truncated = StyledLine::new();
+    source.push_range(&mut truncated, 0, offset(head_end));
+    if let Some(ellipsis) = ellipsis {
+        truncated.push(ellipsis.clone());
+    }
+    source.push_range(&mut truncated, offset(graphemes.len() - tail_count), source.text.len());
+    truncated
+}
+
+/// Cuts text containing SGR escape codes to fit in the given width. See `truncate_styled`.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{truncate_ansi, TruncateOptions, TruncatePosition, highlight_red};
+///
+/// let text = highlight_red("/very/long/path/to/file.txt");
+/// let options = TruncateOptions { position: TruncatePosition::Start, ..TruncateOptions::new(10) };
+/// assert_eq!(truncate_ansi(text.output(), &options), "…\u{001B}[0;31m/file.txt\u{001B}[0m");
+/// ```
+pub fn truncate_ansi(text: &str, options: &TruncateOptions) -> String {
+    truncate_styled(&StyledLine::parse(text), options).output()
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Counts how many of the given widths fit in the available columns
+fn fitting_count<'a>(widths: impl Iterator<Item = &'a usize>, available: usize) -> usize {
+    let mut used = 0;
+    widths.take_while(|width| {
+        used += **width;
+        used <= available
+    }).count()
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn truncated(text: &str, position: TruncatePosition, width: usize) -> String {
+        let options = TruncateOptions { position, ..TruncateOptions::new(width) };
+        truncate_styled(&StyledLine::parse(text), &options).plain()
+    }
+
+    #[test]
+    fn test_truncate_positions() {
+        assert_eq!(truncated("short", TruncatePosition::End, 5), "short");
+        assert_eq!(truncated("abcdefghij", TruncatePosition::End, 5), "abcd…");
+        assert_eq!(truncated("abcdefghij", TruncatePosition::Start, 5), "…ghij");
+        assert_eq!(truncated("abcdefghij", TruncatePosition::Middle, 6), "abc…ij");
+        assert_eq!(truncated("日本語テキスト", TruncatePosition::End, 6), "日本…");
+        assert_eq!(truncated("abc", TruncatePosition::End, 0), "");
+    }
+
+    #[test]
+    fn test_truncate_styles() {
+        let line = StyledLine::parse(&format!("ab{}", TerminalStyle::blue().wrap("cdefgh")));
+        let options = TruncateOptions {
+            position: TruncatePosition::Middle,
+            ellipsis: StyledTerminalText::new("..", TerminalStyle::red()),
+            ..TruncateOptions::new(6)
+        };
+        assert_eq!(
+            truncate_styled(&line, &options).output(),
+            "ab\u{001B}[0;31m..\u{001B}[0;34mgh\u{001B}[0m"
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::ansi::visible_chunks;
+use unicode_segmentation::UnicodeSegmentation;
+use unicode_width::UnicodeWidthChar;
+
+// Width Options --------------------------------------------------------------------------------- /
+
+/// How to measure characters with an "ambiguous" East Asian Width (like `±`, `Ω` or `→`).
+/// These are narrow in most western terminals, but wide in many CJK locales.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AmbiguousWidth {
+    /// Ambiguous characters take one column
+    Narrow,
+    /// Ambiguous characters take two columns
+    Wide,
+}
+
+/// Options used to measure the visible width of text.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{visible_width_with, WidthOptions, AmbiguousWidth};
+///
+/// let options = WidthOptions { ambiguous: AmbiguousWidth::Wide, ..WidthOptions::default() };
+/// assert_eq!(visible_width_with("±1", &options), 3);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WidthOptions {
+    /// Width of ambiguous characters (defaults to narrow)
+    pub ambiguous: AmbiguousWidth,
+    /// Distance between tab stops (defaults to 8). A tab advances to the next tab stop.
+    pub tab_size: usize,
+}
+
+impl Default for WidthOptions {
+
+    fn default() -> Self {
+        WidthOptions {
+            ambiguous: AmbiguousWidth::Narrow,
+            tab_size: 8,
+        }
+    }
+}
+
+impl WidthOptions {
+
+    /// Gets the number of columns a single grapheme cluster occupies (ignoring tabs).
+    pub(crate) fn grapheme_width(&self, grapheme: &str) -> usize {
+        let mut chars = grapheme.chars();
+        let first = match chars.next() {
+            Some(first) => first,
+            None => return 0,
+        };
+        let width = self.char_width(first);
+        let rest = chars.as_str();
+        if rest.is_empty() {
+            return width;
+        }
+        if is_regional_indicator(first) || rest.contains('\u{FE0F}') {
+            // Flags and emoji presentation (VS16) are shown as wide emoji
+            return 2;
+        }
+        if rest.contains('\u{FE0E}') {
+            // Text presentation (VS15)
+            return width.min(1);
+        }
+        // Combining marks and ZWJ-joined emoji take the width of the base character
+        width
+    }
+
+    /// Gets the column after printing the given grapheme cluster starting at `column`.
+    pub(crate) fn advance(&self, column: usize, grapheme: &str) -> usize {
+        if grapheme == "\t" {
+            let tab_size = self.tab_size.max(1);
+            (column / tab_size + 1) * tab_size
+        } else {
+            column + self.grapheme_width(grapheme)
+        }
+    }
+
+    /// Gets the width of a single character (control characters have no width).
+    fn char_width(&self, character: char) -> usize {
+        let width = match self.ambiguous {
+            AmbiguousWidth::Narrow => character.width(),
+            AmbiguousWidth::Wide => character.width_cjk(),
+        };
+        width.unwrap_or(0)
+    }
+}
+
+fn is_regional_indicator(character: char) -> bool {
+    ('\u{1F1E6}'..='\u{1F1FF}').contains(&character)
+}
+
+// Visible width --------------------------------------------------------------------------------- /
+
+/// Gets the number of terminal columns the text occupies, skipping escape sequences and taking
+/// wide characters (CJK, emoji) and zero-width ones (combining marks) into account.
+/// For text with several lines, the widest line is measured.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{visible_width, highlight_red};
+///
+/// let text = highlight_red("日本");
+/// assert_eq!(text.output().len(), 17);
+/// assert_eq!(visible_width(text.output()), 4);
+/// ```
+pub fn visible_width(text: &str) -> usize {
+    visible_width_with(text, &WidthOptions::default())
+}
+
+/// Gets the number of terminal columns the text occupies, using the given options.
+/// See `visible_width`.
+pub fn visible_width_with(text: &str, options: &WidthOptions) -> usize {
+    let mut widest = 0;
+    let mut column = 0;
+    for chunk in visible_chunks(text) {
+        for grapheme in chunk.graphemes(true) {
+            if grapheme == "\n" || grapheme == "\r\n" || grapheme == "\r" {
+                column = 0;
+            } else {
+                column = options.advance(column, grapheme);
+                widest = widest.max(column);
+            }
+        }
+    }
+    widest
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_visible_width() {
+        assert_eq!(visible_width("Hello"), 5);
+        assert_eq!(visible_width("\u{001B}[1;93mHello\u{001B}[0m"), 5);
+        assert_eq!(visible_width("日本語"), 6);
+        assert_eq!(visible_width("e\u{0301}"), 1);
+        assert_eq!(visible_width("👩\u{200D}👩\u{200D}👧"), 2);
+        assert_eq!(visible_width("❤\u{FE0F}"), 2);
+        assert_eq!(visible_width("🇩🇪"), 2);
+        assert_eq!(visible_width("long line\nshort"), 9);
+    }
+
+    #[test]
+    fn test_width_options() {
+        let wide = WidthOptions { ambiguous: AmbiguousWidth::Wide, ..WidthOptions::default() };
+        assert_eq!(visible_width("±"), 1);
+        assert_eq!(visible_width_with("±", &wide), 2);
+        let tabs = WidthOptions { tab_size: 4, ..WidthOptions::default() };
+        assert_eq!(visible_width("ab\tc"), 9);
+        assert_eq!(visible_width_with("ab\tc", &tabs), 5);
+    }
+}
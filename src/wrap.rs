@@ -0,0 +1,207 @@
+use crate::styled_line::{PlainLine, StyledLine};
+use crate::width::{visible_width_with, WidthOptions};
+use unicode_linebreak::{linebreaks, BreakOpportunity};
+use unicode_segmentation::UnicodeSegmentation;
+
+// Wrap Options ---------------------------------------------------------------------------------- /
+
+/// Options used to wrap styled text to a given width.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::WrapOptions;
+///
+/// let options = WrapOptions { subsequent_indent: String::from("  "), ..WrapOptions::new(40) };
+/// assert_eq!(options.width, 40);
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WrapOptions {
+    /// Maximum number of columns of each line (including indentation)
+    pub width: usize,
+    /// Text placed before the first line
+    pub initial_indent: String,
+    /// Text placed before every line after the first (for a hanging indent)
+    pub subsequent_indent: String,
+    /// Whether words longer than the line are broken (otherwise, they overflow the line)
+    pub break_words: bool,
+    /// Options used to measure the width of text
+    pub width_options: WidthOptions,
+}
+
+impl WrapOptions {
+
+    /// Creates options for the given width, without indentation and breaking long words
+    pub fn new(width: usize) -> Self {
+        WrapOptions {
+            width,
+            initial_indent: String::new(),
+            subsequent_indent: String::new(),
+            break_words: true,
+            width_options: WidthOptions::default(),
+        }
+    }
+}
+
+// Wrap ------------------------------------------------------------------------------------------ /
+
+/// Wraps styled text into lines no wider than the given width, breaking at Unicode line break
+/// opportunities (and at newlines). Each line is styled on its own, so its styles are closed at
+/// the end of the line and reopened on the next one. Escape sequences other than styles are dropped.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{wrap_styled, StyledLine, WrapOptions, TerminalStyle};
+///
+/// let mut paragraph = StyledLine::new();
+/// paragraph.push_styled("The quick brown fox", TerminalStyle::red());
+/// paragraph.push_str(" jumps over the lazy dog");
+/// let lines = wrap_styled(&paragraph, &WrapOptions::new(16));
+/// let plain: Vec<String> = lines.iter().map(|line| line.plain()).collect();
+/// assert_eq!(plain, vec!["The quick brown", "fox jumps over", "the lazy dog"]);
+/// assert_eq!(lines[1].output(), "\u{001B}[0;31mfox\u{001B}[0m jumps over");
+/// ```
+pub fn wrap_styled(line: &StyledLine, options: &WrapOptions) -> Vec<StyledLine> {
+    let source = PlainLine::new(line);
+    let plain = &source.text;
+    let mut wrapper = Wrapper {
+        plain,
+        source: &source,
+        options,
+        lines: Vec::new(),
+        line_start: 0,
+        line_end: 0,
+        column: 0,
+    };
+    let mut start = 0;
+    for (end, opportunity) in linebreaks(plain) {
+        wrapper.add_word(start, end);
+        if opportunity == BreakOpportunity::Mandatory && end < plain.len() {
+            wrapper.line_end = end;
+            wrapper.finish_line(end);
+        }
+        start = end;
+    }
+    if wrapper.line_start < plain.len() || wrapper.lines.is_empty() {
+        wrapper.finish_line(plain.len());
+    }
+    wrapper.lines
+}
+
+/// Wraps text containing SGR escape codes into lines no wider than the given width.
+/// See `wrap_styled`.
+///
+/// # Examples
+/// ```
+/// use terminal_text_styler::{wrap_ansi, WrapOptions, highlight_red};
+///
+/// let text = format!("{} and more", highlight_red("styled words"));
+/// let lines = wrap_ansi(&text, &WrapOptions::new(10));
+/// assert_eq!(lines, vec!["\u{001B}[0;31mstyled\u{001B}[0m", "\u{001B}[0;31mwords\u{001B}[0m and", "more"]);
+/// ```
+pub fn wrap_ansi(text: &str, options: &WrapOptions) -> Vec<String> {
+    wrap_styled(&StyledLine::parse(text), options).iter().map(StyledLine::output).collect()
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Collects the lines while wrapping. Each line is a range of the plain text.
+struct Wrapper<'a> {
+    plain: &'a str,
+    source: &'a PlainLine<'a>,
+    options: &'a WrapOptions,
+    lines: Vec<StyledLine>,
+    line_start: usize,
+    line_end: usize,
+    column: usize,
+}
+
+impl<'a> Wrapper<'a> {
+
+    /// Columns available for text on the current line
+    fn available(&self) -> usize {
+        let indent = if self.lines.is_empty() { &self.options.initial_indent } else { &self.options.subsequent_indent };
+        self.options.width.saturating_sub(self.width(indent)).max(1)
+    }
+
+    fn width(&self, text: &str) -> usize {
+        visible_width_with(text, &self.options.width_options)
+    }
+
+    /// Adds the text between two break opportunities
+    fn add_word(&mut self, start: usize, end: usize) {
+        let word = &self.plain[start..end];
+        let content_end = start + word.trim_end_matches(is_newline).len();
+        let trimmed_end = start + word.trim_end().len();
+        let word_width = self.width(&self.plain[start..trimmed_end]);
+        if self.column > 0 && self.column + word_width > self.available() {
+            self.finish_line(start);
+        }
+        if word_width > self.available() && self.options.break_words {
+            for (offset, grapheme) in self.plain[start..trimmed_end].grapheme_indices(true) {
+                let grapheme_width = self.options.width_options.grapheme_width(grapheme);
+                if self.column > 0 && self.column + grapheme_width > self.available() {
+                    self.finish_line(start + offset);
+                }
+                self.column += grapheme_width;
+                self.line_end = start + offset + grapheme.len();
+            }
+            self.column += self.width(&self.plain[trimmed_end..content_end]);
+        } else {
+            self.column += self.width(&self.plain[start..content_end]);
+        }
+        self.line_end = content_end;
+    }
+
+    /// Ends the current line (without trailing whitespace), and starts the next line at `next_start`
+    fn finish_line(&mut self, next_start: usize) {
+        let start = self.line_start;
+        let end = start + self.plain[start..self.line_end.max(start)].trim_end().len();
+        let mut line = StyledLine::new();
+        let indent = if self.lines.is_empty() { &self.options.initial_indent } else { &self.options.subsequent_indent };
+        if !indent.is_empty() {
+            line.push_str(indent);
+        }
+        self.source.push_range(&mut line, start, end);
+        self.lines.push(line);
+        self.line_start = next_start;
+        self.line_end = next_start;
+        self.column = 0;
+    }
+}
+
+fn is_newline(character: char) -> bool {
+    matches!(character, '\n' | '\r' | '\u{0B}' | '\u{0C}' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plain_lines(text: &str, options: &WrapOptions) -> Vec<String> {
+        wrap_styled(&StyledLine::parse(text), options).iter().map(StyledLine::plain).collect()
+    }
+
+    #[test]
+    fn test_wrap_plain() {
+        assert_eq!(plain_lines("", &WrapOptions::new(10)), vec![""]);
+        assert_eq!(plain_lines("one two\nthree four five", &WrapOptions::new(10)), vec!["one two", "three four", "five"]);
+        assert_eq!(plain_lines("a supercalifragilistic word", &WrapOptions::new(8)), vec!["a", "supercal", "ifragili", "stic", "word"]);
+        let no_break = WrapOptions { break_words: false, ..WrapOptions::new(8) };
+        assert_eq!(plain_lines("a supercalifragilistic word", &no_break), vec!["a", "supercalifragilistic", "word"]);
+        assert_eq!(plain_lines("日本語のテキスト", &WrapOptions::new(6)), vec!["日本語", "のテキ", "スト"]);
+    }
+
+    #[test]
+    fn test_wrap_indent() {
+        let options = WrapOptions {
+            initial_indent: String::from("- "),
+            subsequent_indent: String::from("  "),
+            ..WrapOptions::new(12)
+        };
+        assert_eq!(plain_lines("first second third fourth", &options), vec!["- first", "  second", "  third", "  fourth"]);
+        let options = WrapOptions { initial_indent: String::from("- "), ..WrapOptions::new(14) };
+        assert_eq!(plain_lines("first second third fourth", &options), vec!["- first second", "third fourth"]);
+    }
+}
@@ -0,0 +1,454 @@
+use crate::ansi::{ansi_segments, sgr_codes, AnsiSegment};
+use crate::detect::{ColorLevel, Stream};
+use crate::render::{downsample_codes, override_render_policy, write_sgr, write_style, RenderPolicy, RESET};
+use crate::sgr_state::SGRState;
+use crate::styled_line::StyledLine;
+use crate::terminal_style::TerminalStyle;
+use std::borrow::Cow;
+use std::fmt;
+use std::io::{self, Write};
+use std::str;
+
+// Styled Writer --------------------------------------------------------------------------------- /
+
+/// Writes styled text to an `io::Write`, keeping track of the style the terminal is in so only the
+/// escape codes needed to change it are written. Style changes are written lazily, just before
+/// the next text, so styles set and replaced without text in between cost nothing.
+///
+/// Bytes written through `io::Write` are passed through in the current style. SGR escape codes in
+/// them are rewritten for the writer's color level (so with `ColorLevel::None` they're left out),
+/// then followed (as long as they aren't split between writes) and take effect until they reset
+/// the terminal. Styled values written with `write!` are formatted for the writer's color level,
+/// whatever the render policy. The terminal is reset when the writer is dropped, or when writing
+/// fails.
+///
+/// # Examples
+/// ```
+/// use std::io::Write;
+/// use terminal_text_styler::{StyledWriter, TerminalStyle};
+///
+/// let mut writer = StyledWriter::new(Vec::new());
+/// writer.set_style(&TerminalStyle::from(vec![1]));
+/// write!(writer, "Total: ").unwrap();
+/// writer.push_style(&TerminalStyle::green());
+/// write!(writer, "42").unwrap();
+/// writer.pop_style();
+/// writer.write_styled(" (ok)", &TerminalStyle::from(vec![1])).unwrap();
+/// let output = writer.into_inner().unwrap();
+/// assert_eq!(
+///     String::from_utf8(output).unwrap(),
+///     "\u{001B}[1mTotal: \u{001B}[32m42\u{001B}[39m (ok)\u{001B}[0m"
+/// );
+/// ```
+#[derive(Debug)]
+pub struct StyledWriter<W: Write> {
+    /// Only taken by `into_inner`
+    inner: Option<W>,
+    /// How escape codes are written (for standard streams, with the stream's color level and
+    /// only the effects its terminfo entry has)
+    policy: RenderPolicy,
+    /// The style of the terminal, as of the last escape codes written
+    written: SGRState,
+    /// The style of the next text
+    current: SGRState,
+    /// The current style when escape codes written as is changed the terminal's style, which is
+    /// left to them until they reset it (or the current style changes)
+    passthrough: Option<SGRState>,
+    /// Styles to go back to, for each `push_style`
+    stack: Vec<SGRState>,
+}
+
+impl<W: Write> StyledWriter<W> {
+
+    // Init -------------------------------------------------------------------------------------- /
+
+    /// Creates a writer that writes all escape codes, with 24-bit colors
+    pub fn new(inner: W) -> Self {
+        StyledWriter::with_color_level(inner, ColorLevel::TrueColor)
+    }
+
+    /// Creates a writer for a color level: colors the level doesn't have are replaced by the
+    /// closest ones it has, and with `ColorLevel::None` no escape codes are written.
+    pub fn with_color_level(inner: W, level: ColorLevel) -> Self {
+        StyledWriter::with_policy(inner, RenderPolicy::always().max_level(level))
+    }
+
+    /// Creates a writer for a standard stream, with its color level and without the effects its
+    /// terminfo entry has no capability for
+    pub(crate) fn for_stream(inner: W, stream: Stream) -> Self {
+        StyledWriter::with_policy(inner, RenderPolicy::auto(stream))
+    }
+
+    /// Creates a writer that writes escape codes following a render policy
+    fn with_policy(inner: W, policy: RenderPolicy) -> Self {
+        StyledWriter {
+            inner: Some(inner),
+            policy,
+            written: SGRState::new(),
+            current: SGRState::new(),
+            passthrough: None,
+            stack: Vec::new(),
+        }
+    }
+
+    // Accessors --------------------------------------------------------------------------------- /
+
+    /// Gets the color level escape codes are written for
+    pub fn color_level(&self) -> ColorLevel {
+        self.policy.color_level()
+    }
+
+    /// Gets the style of the next text written
+    pub fn style(&self) -> TerminalStyle {
+        self.current.to_style()
+    }
+
+    /// Gets the underlying writer
+    pub fn get_ref(&self) -> &W {
+        self.inner.as_ref().expect("the writer is only taken when consumed")
+    }
+
+    /// Gets the underlying writer. Writing to it directly can leave the tracked style wrong.
+    pub fn get_mut(&mut self) -> &mut W {
+        self.inner.as_mut().expect("the writer is only taken when consumed")
+    }
+
+    /// Resets the terminal, then gets the underlying writer
+    pub fn into_inner(mut self) -> io::Result<W> {
+        self.reset()?;
+        Ok(self.inner.take().expect("the writer is only taken when consumed"))
+    }
+
+    // Styling ----------------------------------------------------------------------------------- /
+
+    /// Sets the style of the text written next
+    pub fn set_style(&mut self, style: &TerminalStyle) {
+        self.current = SGRState::from_style(style);
+    }
+
+    /// Starts a nested region: adds the style to the current one (a red style on bold text gives
+    /// bold red text) until `pop_style` is called.
+    pub fn push_style(&mut self, style: &TerminalStyle) {
+        self.stack.push(self.current.clone());
+        // The style is overlaid, so it doesn't start with a reset
+        let codes: Vec<u8> = style.codes().iter().copied().skip_while(|code| *code == 0).collect();
+        self.current.apply(&codes);
+    }
+
+    /// Ends the innermost nested region, going back to the style it started with.
+    /// Returns `false` (and changes nothing) if there was no region.
+    pub fn pop_style(&mut self) -> bool {
+        match self.stack.pop() {
+            Some(previous) => {
+                self.current = previous;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Writes text in the given style, then goes back to the current style for the next text
+    pub fn write_styled(&mut self, text: &str, style: &TerminalStyle) -> io::Result<()> {
+        self.write_in(text.as_bytes(), &SGRState::from_style(style))
+    }
+
+    /// Writes each span of a line in its style, then goes back to the current style for the next text
+    pub fn write_line(&mut self, line: &StyledLine) -> io::Result<()> {
+        for span in line.spans() {
+            self.write_styled(span.text(), span.style())?;
+        }
+        Ok(())
+    }
+
+    /// Drops all nested regions and resets the terminal to no styling
+    pub fn reset(&mut self) -> io::Result<()> {
+        self.stack.clear();
+        self.current = SGRState::new();
+        self.passthrough = None;
+        if !self.written.is_default() {
+            self.written = SGRState::new();
+            self.get_mut().write_all(RESET.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    // Private instance methods ------------------------------------------------------------------ /
+
+    /// Writes bytes in a style, changing the terminal's style first if needed
+    fn write_in(&mut self, bytes: &[u8], state: &SGRState) -> io::Result<()> {
+        if bytes.is_empty() {
+            return Ok(());
+        }
+        self.switch_to(state)?;
+        let result = self.get_mut().write_all(bytes);
+        self.reset_on_error(result)
+    }
+
+    /// Writes the escape codes that switch the terminal to a style
+    fn switch_to(&mut self, state: &SGRState) -> io::Result<()> {
+        let level = self.color_level();
+        if level == ColorLevel::None {
+            return Ok(());
+        }
+        let transition = match self.written.transition_to(state) {
+            Some(transition) => transition,
+            None => return Ok(()),
+        };
+        let mut command = String::new();
+        // Writing to a `String` can't fail
+        let _ = write_style(&mut command, transition.codes(), Some(transition.command()), level, self.policy.terminfo());
+        let result = self.get_mut().write_all(command.as_bytes());
+        self.written = state.clone();
+        self.passthrough = None;
+        self.reset_on_error(result)
+    }
+
+    /// Takes bytes written through `io::Write` up to where their SGR escape codes leave the
+    /// terminal out of the current style (so the rest is written after switching back to it),
+    /// following the escape codes and rewriting them for the color level. Gives how many of the
+    /// bytes were taken and what to write for them. Only UTF-8 text is rewritten, so the bytes are
+    /// taken up to the first one that isn't UTF-8.
+    fn pass_through<'b>(&mut self, bytes: &'b [u8]) -> (usize, Cow<'b, [u8]>) {
+        if !has_escapes(bytes) {
+            return (bytes.len(), Cow::Borrowed(bytes));
+        }
+        let text = match str::from_utf8(bytes) {
+            Ok(text) => text,
+            // Bytes that aren't UTF-8 at the start can't be part of an escape code
+            Err(error) if error.valid_up_to() == 0 => {
+                let length = error.error_len().unwrap_or(bytes.len());
+                return (length, Cow::Borrowed(&bytes[..length]));
+            }
+            Err(error) => str::from_utf8(&bytes[..error.valid_up_to()]).unwrap_or_default(),
+        };
+        let (level, terminfo) = (self.color_level(), self.policy.terminfo());
+        let mut rewritten = String::with_capacity(text.len());
+        let mut length = 0;
+        let mut changed = false;
+        for segment in ansi_segments(text) {
+            let (part, codes) = match segment {
+                AnsiSegment::Escape(escape) => (escape, sgr_codes(escape)),
+                AnsiSegment::Text(text) => (text, None),
+            };
+            length += part.len();
+            let codes = match codes {
+                Some(codes) => codes,
+                None => {
+                    rewritten.push_str(part);
+                    continue;
+                }
+            };
+            let downsampled = downsample_codes(&codes, level, terminfo);
+            if downsampled == codes {
+                rewritten.push_str(part);
+            } else {
+                changed = true;
+                // Writing to a `String` can't fail
+                let _ = write_sgr(&mut rewritten, &downsampled);
+            }
+            if !downsampled.is_empty() {
+                self.written.apply(&downsampled);
+                self.passthrough = if self.written.is_default() { None } else { Some(self.current.clone()) };
+                if self.passthrough.is_none() && self.written != self.current {
+                    break;
+                }
+            }
+        }
+        if changed {
+            (length, Cow::Owned(rewritten.into_bytes()))
+        } else {
+            (length, Cow::Borrowed(&bytes[..length]))
+        }
+    }
+
+    /// Passes the result on, resetting the terminal first if it's an error
+    fn reset_on_error(&mut self, result: io::Result<()>) -> io::Result<()> {
+        if result.is_err() {
+            self.reset_after_error();
+        }
+        result
+    }
+
+    /// Tries to reset the terminal after an error, so it isn't left styled
+    fn reset_after_error(&mut self) {
+        self.passthrough = None;
+        if !self.written.is_default() {
+            self.written = SGRState::new();
+            let _ = self.get_mut().write_all(RESET.as_bytes());
+        }
+    }
+}
+
+impl<W: Write> Write for StyledWriter<W> {
+
+    /// Writes the bytes in the current style, with their SGR escape codes rewritten for the color
+    /// level. The escape codes are followed, so the current style is restored once they reset the
+    /// terminal.
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        if buf.is_empty() {
+            return Ok(0);
+        }
+        if self.passthrough.as_ref() != Some(&self.current) {
+            let current = self.current.clone();
+            self.switch_to(&current)?;
+        }
+        let (length, bytes) = self.pass_through(buf);
+        match self.get_mut().write_all(&bytes) {
+            Ok(()) => Ok(length),
+            Err(error) => {
+                self.reset_after_error();
+                Err(error)
+            }
+        }
+    }
+
+    /// Formats the text following the writer's render policy (so styled values are written for
+    /// its color level, whatever the policy of the thread), then writes it all at once, so escape
+    /// codes aren't split between writes.
+    fn write_fmt(&mut self, args: fmt::Arguments<'_>) -> io::Result<()> {
+        let text = {
+            let _guard = override_render_policy(self.policy);
+            fmt::format(args)
+        };
+        self.write_all(text.as_bytes())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.get_mut().flush()
+    }
+}
+
+impl<W: Write> Drop for StyledWriter<W> {
+
+    /// Resets the terminal, if the writer wasn't consumed
+    fn drop(&mut self) {
+        if self.inner.is_some() {
+            let _ = self.reset();
+            let _ = self.get_mut().flush();
+        }
+    }
+}
+
+// Private helpers ------------------------------------------------------------------------------- /
+
+/// Whether bytes might contain escape codes (an ESC or a CSI byte)
+fn has_escapes(bytes: &[u8]) -> bool {
+    bytes.iter().any(|&byte| byte == 0x1B || byte == 0x9B)
+}
+
+// Tests ----------------------------------------------------------------------------------------- /
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Stylize;
+
+    /// Fails one of the writes
+    struct Failing {
+        written: Vec<u8>,
+        calls: usize,
+        failing_call: usize,
+    }
+
+    impl Write for Failing {
+
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.calls += 1;
+            if self.calls == self.failing_call {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn written(writer: StyledWriter<Vec<u8>>) -> String {
+        String::from_utf8(writer.into_inner().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_minimal_transitions() {
+        let mut writer = StyledWriter::new(Vec::new());
+        writer.set_style(&TerminalStyle::red());
+        writer.set_style(&TerminalStyle::blue());
+        writer.write_all(b"a").unwrap();
+        writer.write_styled("b", &TerminalStyle::blue()).unwrap();
+        writer.push_style(&TerminalStyle::from(vec![1]));
+        writer.write_all(b"c").unwrap();
+        writer.push_style(&TerminalStyle::red());
+        writer.write_all(b"d").unwrap();
+        assert!(writer.pop_style());
+        assert!(writer.pop_style());
+        assert!(!writer.pop_style());
+        writer.write_all(b"e").unwrap();
+        writer.write_all(b"").unwrap();
+        writer.write_all(b"\x1B[0m").unwrap();
+        writer.write_all(b"f").unwrap();
+        assert_eq!(written(writer), "\u{001B}[34mab\u{001B}[1mc\u{001B}[31md\u{001B}[0;34me\u{001B}[0m\u{001B}[34mf\u{001B}[0m");
+    }
+
+    #[test]
+    fn test_styled_values() {
+        let mut writer = StyledWriter::new(Vec::new());
+        writer.set_style(&TerminalStyle::green());
+        write!(writer, "{} ", "error".style(&TerminalStyle::red())).unwrap();
+        let mut line = StyledLine::new();
+        line.push_styled("a", TerminalStyle::red());
+        line.push_styled("b", TerminalStyle::blue());
+        write!(writer, "{}", line).unwrap();
+        writer.write_all(b"\x1B[1mc").unwrap();
+        writer.push_style(&TerminalStyle::from(vec![4]));
+        writer.write_all(b"d").unwrap();
+        assert_eq!(
+            written(writer),
+            "\u{001B}[32m\u{001B}[0;31merror\u{001B}[0m\u{001B}[32m \u{001B}[0;31ma\u{001B}[0;34mb\u{001B}[0m\
+             \u{001B}[32m\u{001B}[1mc\u{001B}[22;4md\u{001B}[0m"
+        );
+    }
+
+    #[test]
+    fn test_color_levels() {
+        let style = TerminalStyle::from(vec![38, 2, 255, 0, 0]);
+        let mut writer = StyledWriter::with_color_level(Vec::new(), ColorLevel::Ansi16);
+        writer.write_styled("hot", &style).unwrap();
+        assert_eq!(written(writer), "\u{001B}[91mhot\u{001B}[0m");
+        let mut writer = StyledWriter::with_color_level(Vec::new(), ColorLevel::None);
+        writer.write_styled("hot", &style).unwrap();
+        assert_eq!(written(writer), "hot");
+        // Styled values written as is follow the writer's level, whatever the render policy
+        let mut writer = StyledWriter::with_color_level(Vec::new(), ColorLevel::None);
+        writer.set_style(&TerminalStyle::green());
+        writeln!(writer, "{} {}", "x".red().bold(), "y".rgb(255, 0, 0)).unwrap();
+        writer.write_all(TerminalStyle::blue().wrap("z").as_bytes()).unwrap();
+        assert_eq!(written(writer), "x y\nz");
+        let _guard = override_render_policy(RenderPolicy::never());
+        let mut writer = StyledWriter::new(Vec::new());
+        write!(writer, "{}", "x".red()).unwrap();
+        assert_eq!(written(writer), "\u{001B}[31mx\u{001B}[0m");
+        let mut writer = StyledWriter::with_color_level(Vec::new(), ColorLevel::Ansi16);
+        writer.write_all("é \x1B[38;2;255;0;0mhot\x1B[0m".as_bytes()).unwrap();
+        writer.write_all(b"\xFF\x1B[38;5;196m!").unwrap();
+        assert_eq!(writer.into_inner().unwrap(), b"\xC3\xA9 \x1B[91mhot\x1B[0m\xFF\x1B[91m!\x1B[0m".to_vec());
+    }
+
+    #[test]
+    fn test_resets() {
+        let mut output = Vec::new();
+        {
+            let mut writer = StyledWriter::new(&mut output);
+            writer.set_style(&TerminalStyle::red());
+            writer.write_all(b"a").unwrap();
+        }
+        assert_eq!(output, b"\x1B[31ma\x1B[0m");
+        let mut writer = StyledWriter::new(Failing { written: Vec::new(), calls: 0, failing_call: 2 });
+        writer.set_style(&TerminalStyle::red());
+        assert!(writer.write_styled("a", &TerminalStyle::blue()).is_err());
+        assert_eq!(writer.get_ref().written, b"\x1B[34m\x1B[0m");
+        writer.write_all(b"b").unwrap();
+        assert_eq!(writer.get_ref().written, b"\x1B[34m\x1B[0m\x1B[31mb");
+    }
+}